@@ -0,0 +1,566 @@
+//! Command-line access to the strokes in an AI.cfg file, for working over SSH and in scripts.
+
+use anyhow::{anyhow, bail, Context};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::exit;
+use stroke_preview::data::{load_cfg_data, load_default_files, CfgData, Files, StrokeError};
+use stroke_preview::diff::{diff_cfg_data, StrokeDiff};
+use stroke_preview::edit::FieldPath;
+use stroke_preview::lint::{self, Severity, RULES};
+use stroke_preview::list_file::ListEntry;
+use stroke_preview::offsets::{over_budget, LocatedStroke};
+use stroke_preview::raster;
+use stroke_preview::sheet::{render_sheet_html, render_sheet_svg, sheet_entries};
+use stroke_preview::strokes::{Stroke, StrokeTiming};
+use stroke_preview::svg::render_stroke_with_overlay;
+
+/// the largest `--size`, a single diagram of which is all the pixels `raster::MAX_PIXELS` allows
+const MAX_SIZE: u32 = 4096;
+
+const USAGE: &str = "usage: stroke_cli [OPTIONS] COMMAND
+
+commands:
+    list                 list every stroke with its cfg line number, List.txt offset and how
+                         much of its slot in the .gob file it uses
+    show NAME|INDEX      show all fields and keyframes of one stroke
+    sections             list every List.txt entry, not just the strokes, and every block of
+                         the cfg file that no entry points at
+    block NAME|INDEX     print the text of any section from the cfg file as it is, with INDEX
+                         as given by sections
+    check                parse every stroke and report the ones that fail or are too long for
+                         their slot
+    audit                check List.txt for entries that share an offset, slots that overlap,
+                         numbered entries out of order and strokes whose offset points into
+                         the middle of a block; only strokes are checked for that last one
+    lint [NAME|INDEX]    check every stroke, or one, for likely mistakes
+    rules                list the lint rules with their IDs and severities
+    set NAME|INDEX FIELD VALUE
+                         change one value of a stroke in the cfg file, leaving the rest of the
+                         file as it is. FIELD is a header field like edge_prob, or a keyframe
+                         field like normal.2.direction_area or 6hit.0.power
+    diff OLD NEW         compare the strokes of two cfg files field by field, matching them by
+                         their List.txt description
+    svg NAME|INDEX       draw the pitch diagram of one stroke as SVG
+    png NAME|INDEX       draw the pitch diagram of one stroke as PNG
+    png-all DIRECTORY    write a PNG contact sheet for every stroke into DIRECTORY
+    sheet                draw every stroke in one labelled SVG grid, marking and failing on
+                         any that can't be parsed
+
+options:
+    --cfg PATH           the exported AI.cfg file
+    --list PATH          the List.txt file
+    --6hit               draw the 6Hit keyframes instead of the Normal ones
+    --keyframe N         the keyframe to highlight, from 0 (very early) to 4 (very late)
+    --sheet              draw all ten keyframes in a grid, Normal above 6Hit
+    --html               make sheet write an HTML page instead of SVG
+    --json               make diff write JSON instead of text
+    --compare PATH       make svg outline the same stroke from another cfg file on top
+    --tolerant           accept hand edited strokes with keys in any order, signed values and
+                         comments, warning about anything Config Editor wouldn't write
+    --force              make set write a stroke even if it no longer fits its slot
+    --size N             the size of each diagram in pixels, from 11 to 4096 and 400 by default;
+                         a PNG contact sheet of ten diagrams allows at most 1295
+    -o, --output PATH    write to a file instead of standard output
+
+Paths default to those in stroke_preview.toml, or AI.cfg and List.txt.";
+
+struct Options {
+    selected_6hit: bool,
+    selected_timing: usize,
+    sheet: bool,
+    html: bool,
+    json: bool,
+    compare: Option<String>,
+    tolerant: bool,
+    force: bool,
+    size: u32,
+    output: Option<String>,
+}
+
+fn main() {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(true) => {}
+        Ok(false) => exit(1),
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            exit(2);
+        }
+    }
+}
+
+/// runs the command line, returning whether everything that was checked was ok
+fn run(args: Vec<String>) -> anyhow::Result<bool> {
+    let mut files = load_default_files()?;
+    let mut options = Options {
+        selected_6hit: false,
+        selected_timing: 2,
+        sheet: false,
+        html: false,
+        json: false,
+        compare: None,
+        tolerant: false,
+        force: false,
+        size: 400,
+        output: None,
+    };
+    let mut command = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cfg" => files.cfg_file = args.next().context("--cfg needs a path")?,
+            "--list" => files.list_file = args.next().context("--list needs a path")?,
+            "--6hit" => options.selected_6hit = true,
+            "--keyframe" => {
+                options.selected_timing = args
+                    .next()
+                    .and_then(|k| k.parse().ok())
+                    .filter(|&k| k < 5)
+                    .context("--keyframe needs a number from 0 to 4")?
+            }
+            "--sheet" => options.sheet = true,
+            "--html" => options.html = true,
+            "--json" => options.json = true,
+            "--compare" => options.compare = Some(args.next().context("--compare needs a path")?),
+            "--tolerant" => options.tolerant = true,
+            "--force" => options.force = true,
+            "--size" => {
+                options.size = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|s| (11..=MAX_SIZE).contains(s))
+                    .with_context(|| {
+                        format!("--size needs a number of pixels from 11 to {}", MAX_SIZE)
+                    })?
+            }
+            "-o" | "--output" => {
+                options.output = Some(args.next().context("--output needs a path")?)
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(true);
+            }
+            _ => command.push(arg),
+        }
+    }
+
+    if options.compare.is_some() && command.first().map(String::as_str) != Some("svg") {
+        bail!("--compare only works with svg");
+    }
+
+    let data = || load_cfg_data(&files);
+    match command.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["list"] => list(&data()?),
+        ["show", stroke] => show(&data()?, stroke, &options)?,
+        ["sections"] => sections(&data()?),
+        ["block", section] => block(&data()?, section)?,
+        ["check"] => return Ok(check(&data()?, &options)),
+        ["audit"] => return Ok(audit(&data()?)),
+        ["lint"] => return lint(&data()?, None, &options),
+        ["lint", stroke] => return lint(&data()?, Some(stroke), &options),
+        ["rules"] => rules(),
+        ["set", stroke, field, value] => set(&files, &data()?, stroke, field, value, &options)?,
+        ["diff", old, new] => return diff(&files, old, new, &options),
+        ["svg", stroke] => svg(&files, &data()?, stroke, &options)?,
+        ["png", stroke] => png(&data()?, stroke, &options)?,
+        ["png-all", directory] => return png_all(&data()?, directory, &options),
+        ["sheet"] => return sheet(&data()?, &options),
+        _ => bail!("{}", USAGE),
+    }
+    Ok(true)
+}
+
+fn list(data: &CfgData) {
+    println!("{:>5} {:>6} {:>10} {:>11}  name", "index", "line", "offset", "bytes");
+    for (i, entry) in data.cfg_items.iter().enumerate() {
+        let needed =
+            data.stroke_locations[i].map_or("?".to_string(), |s| s.bytes_needed().to_string());
+        let bytes = format!("{}/{}", needed, entry.slot_bytes());
+        println!(
+            "{:>5} {:>6} {:>10} {:>11}  {}",
+            i,
+            data.line_label(i),
+            entry.offset,
+            bytes,
+            entry.description
+        );
+    }
+}
+
+/// parses the `i`th stroke, tolerantly if asked, printing any warnings to standard error
+fn parse_stroke(data: &CfgData, i: usize, options: &Options) -> anyhow::Result<Stroke> {
+    let result = if options.tolerant {
+        data.parse_stroke_tolerant(i).map(|(stroke, warnings)| {
+            for warning in warnings {
+                eprintln!("warning: {}: {}", data.cfg_items[i].description.trim(), warning);
+            }
+            stroke
+        })
+    } else {
+        data.parse_stroke(i)
+    };
+    result.map_err(|e| anyhow!("{}", describe_error(data, i, &e)))
+}
+
+/// names the stroke, says what went wrong and shows the offending line when there is one
+fn describe_error(data: &CfgData, i: usize, e: &StrokeError) -> String {
+    let description = data.cfg_items[i].description.trim();
+    match &e.snippet {
+        Some(snippet) => format!("{}: {}\n{}", description, e, snippet),
+        None => format!("line {}: {}: {}", data.line_label(i), description, e),
+    }
+}
+
+/// finds a stroke by its List.txt description, ignoring case, or by its index in `list`
+fn find_stroke(data: &CfgData, name_or_index: &str) -> anyhow::Result<usize> {
+    data.cfg_items
+        .iter()
+        .position(|e| e.description.trim().eq_ignore_ascii_case(name_or_index.trim()))
+        .or_else(|| name_or_index.parse().ok().filter(|&i| i < data.cfg_items.len()))
+        .ok_or_else(|| anyhow!("no stroke named {:?}", name_or_index))
+}
+
+fn sections(data: &CfgData) {
+    println!("{:>5} {:>6} {:>10}  section", "index", "line", "offset");
+    for (i, section) in data.sections.iter().enumerate() {
+        let line = section.line_number.map_or_else(|| "-".to_string(), |l| l.to_string());
+        let offset =
+            section.entry.as_ref().map_or_else(|| "-".to_string(), |e| e.offset.to_string());
+        println!("{:>5} {:>6} {:>10}  {}", i, line, offset, section);
+    }
+}
+
+fn block(data: &CfgData, name_or_index: &str) -> anyhow::Result<()> {
+    let named = |e: &ListEntry| e.description.trim().eq_ignore_ascii_case(name_or_index.trim());
+    let i = data
+        .sections
+        .iter()
+        .position(|s| matches!(&s.entry, Some(e) if named(e)))
+        .or_else(|| name_or_index.parse().ok().filter(|&i| i < data.sections.len()))
+        .ok_or_else(|| anyhow!("no section named {:?}", name_or_index))?;
+    print!("{}", data.section_text(i)?);
+    Ok(())
+}
+
+fn show(data: &CfgData, name_or_index: &str, options: &Options) -> anyhow::Result<()> {
+    let i = find_stroke(data, name_or_index)?;
+    let entry = &data.cfg_items[i];
+    let stroke = parse_stroke(data, i, options)?;
+    println!("{}", entry.description);
+    println!("line {}, offset {}", data.line_label(i), entry.offset);
+    println!();
+    print_stroke(&stroke);
+    Ok(())
+}
+
+fn svg(
+    files: &Files,
+    data: &CfgData,
+    name_or_index: &str,
+    options: &Options,
+) -> anyhow::Result<()> {
+    let i = find_stroke(data, name_or_index)?;
+    let stroke = parse_stroke(data, i, options)?;
+    let overlay = match &options.compare {
+        Some(cfg_file) => {
+            let other = load_other_cfg_data(files, cfg_file)?;
+            let description = &data.cfg_items[i].description;
+            let j = other.position_of(description).ok_or_else(|| {
+                anyhow!("{} has no stroke named {:?}", cfg_file, description.trim())
+            })?;
+            Some(parse_stroke(&other, j, options)?)
+        }
+        None => None,
+    };
+    let svg = render_stroke_with_overlay(
+        Some(&stroke),
+        overlay.as_ref(),
+        options.selected_timing,
+        options.selected_6hit,
+        options.size,
+    );
+    write_output(options, svg.as_bytes())
+}
+
+/// loads another cfg file with the same List.txt
+fn load_other_cfg_data(files: &Files, cfg_file: &str) -> anyhow::Result<CfgData> {
+    let files = Files {
+        cfg_file: cfg_file.to_string(),
+        list_file: files.list_file.clone(),
+        reference_cfg_file: None,
+    };
+    load_cfg_data(&files).with_context(|| format!("could not load {}", cfg_file))
+}
+
+fn png(data: &CfgData, name_or_index: &str, options: &Options) -> anyhow::Result<()> {
+    let i = find_stroke(data, name_or_index)?;
+    let stroke = parse_stroke(data, i, options)?;
+    let image = if options.sheet {
+        check_contact_sheet_size(options.size)?;
+        raster::render_contact_sheet(&stroke, options.size)
+    } else {
+        raster::render_stroke(
+            Some(&stroke),
+            options.selected_timing,
+            options.selected_6hit,
+            options.size,
+        )
+    };
+    let mut png = Vec::new();
+    image.write_png(&mut png)?;
+    write_output(options, &png)
+}
+
+/// writes a contact sheet for every stroke, returning whether all of them parsed
+fn png_all(data: &CfgData, directory: &str, options: &Options) -> anyhow::Result<bool> {
+    check_contact_sheet_size(options.size)?;
+    fs::create_dir_all(directory).with_context(|| format!("could not create {}", directory))?;
+    let mut all_parsed = true;
+    for (i, entry) in data.cfg_items.iter().enumerate() {
+        let stroke = match parse_stroke(data, i, options) {
+            Ok(stroke) => stroke,
+            Err(e) => {
+                println!("{}", e);
+                all_parsed = false;
+                continue;
+            }
+        };
+        let file_name: String = entry
+            .description
+            .trim()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect();
+        let path = Path::new(directory).join(format!("{:03}_{}.png", i, file_name));
+        let file = File::create(&path).with_context(|| format!("could not create {:?}", path))?;
+        raster::render_contact_sheet(&stroke, options.size).write_png(BufWriter::new(file))?;
+    }
+    Ok(all_parsed)
+}
+
+/// fails if a PNG contact sheet of `size` pixel diagrams would be more than the rasteriser draws
+fn check_contact_sheet_size(size: u32) -> anyhow::Result<()> {
+    let (width, height) = raster::contact_sheet_dimensions(size);
+    if u64::from(width) * u64::from(height) > raster::MAX_PIXELS {
+        bail!(
+            "a contact sheet of {} pixel diagrams would be {} by {} pixels, over the limit of {}; \
+             use a smaller --size",
+            size,
+            width,
+            height,
+            raster::MAX_PIXELS
+        );
+    }
+    Ok(())
+}
+
+fn sheet(data: &CfgData, options: &Options) -> anyhow::Result<bool> {
+    let entries = sheet_entries(data, options.tolerant);
+    let mut all_parsed = true;
+    for (i, entry) in entries.iter().enumerate() {
+        for warning in &entry.warnings {
+            eprintln!("warning: {}: {}", data.cfg_items[i].description.trim(), warning);
+        }
+        if let Err(e) = &entry.stroke {
+            eprintln!("{}", describe_error(data, i, e));
+            all_parsed = false;
+        }
+    }
+    let (timing, selected_6hit) = (options.selected_timing, options.selected_6hit);
+    let sheet = if options.html {
+        render_sheet_html(&entries, timing, selected_6hit, options.size)
+    } else {
+        render_sheet_svg(&entries, timing, selected_6hit, options.size)
+    };
+    write_output(options, sheet.as_bytes())?;
+    Ok(all_parsed)
+}
+
+fn write_output(options: &Options, contents: &[u8]) -> anyhow::Result<()> {
+    match &options.output {
+        Some(path) => {
+            fs::write(path, contents).with_context(|| format!("could not write {}", path))
+        }
+        None => Ok(io::stdout().write_all(contents)?),
+    }
+}
+
+fn print_stroke(stroke: &Stroke) {
+    println!("Type           {}", stroke.stroke_type);
+    println!("EdgeModifier   {}", stroke.edge_modifier);
+    if let Some(edge_modifier6) = stroke.edge_modifier6 {
+        println!("EdgeModifier6  {}", edge_modifier6);
+    }
+    println!("Difficulty     {:<10} Reward     {}", stroke.difficulty, stroke.reward);
+    println!("Difficulty6    {:<10} Reward6    {}", stroke.difficulty6, stroke.reward6);
+    println!("EdgeProb       {:<10} EdgeProb6  {}", stroke.edge_prob, stroke.edge_prob6);
+    println!("BowlerTypes    {}", stroke.bowler_types);
+    println!("BallStumps     {}", stroke.ball_stumps);
+    println!("BallBatsman    {}", stroke.ball_batsman);
+    println!("BallLength     {}", stroke.ball_length);
+    println!();
+    println!(
+        "{:<6} {:>8} {:>5} {:>10} {:>10} {:>13} {:>10} {:>10}",
+        "Mode", "KeyFrame", "Frame", "Vertical", "Direction", "DirectionArea", "Power", "PowerArea"
+    );
+    for (mode, timings) in
+        [("Normal", &stroke.timings_normal), ("6Hit", &stroke.timings_6hit)].iter()
+    {
+        for (i, t) in timings.iter().enumerate() {
+            print_timing(mode, i, t);
+        }
+    }
+}
+
+fn print_timing(mode: &str, i: usize, t: &StrokeTiming) {
+    println!(
+        "{:<6} {:>8} {:>5} {:>10} {:>10} {:>13} {:>10} {:>10}",
+        mode, i, t.frame, t.vertical, t.direction, t.direction_area, t.power, t.power_area
+    );
+}
+
+fn check(data: &CfgData, options: &Options) -> bool {
+    for error in &data.list_errors {
+        println!("{}", error);
+    }
+    println!("{}", data.calibration);
+    for warning in &data.location_warnings {
+        println!("warning: {}", warning);
+    }
+    let mut failures = 0;
+    let mut too_long = 0;
+    for i in 0..data.cfg_items.len() {
+        if let Some(warning) = data.budget_warning(i) {
+            too_long += 1;
+            println!("warning: {}", warning);
+        }
+        let result = if options.tolerant {
+            data.parse_stroke_tolerant(i).map(|(_, warnings)| warnings)
+        } else {
+            data.parse_stroke(i).map(|_| Vec::new())
+        };
+        match result {
+            Ok(warnings) => {
+                for warning in warnings {
+                    println!("warning: {}: {}", data.cfg_items[i].description.trim(), warning);
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                println!("{}", describe_error(data, i, &e));
+            }
+        }
+    }
+    println!("{} of {} strokes parsed", data.cfg_items.len() - failures, data.cfg_items.len());
+    if too_long > 0 {
+        println!(
+            "{} of {} strokes are too long for Config Editor to import",
+            too_long,
+            data.cfg_items.len()
+        );
+    }
+    failures == 0 && too_long == 0 && data.list_errors.is_empty()
+}
+
+/// lints every stroke, or just the named one, returning whether everything parsed without errors
+fn lint(data: &CfgData, name_or_index: Option<&str>, options: &Options) -> anyhow::Result<bool> {
+    let strokes = match name_or_index {
+        Some(name_or_index) => vec![find_stroke(data, name_or_index)?],
+        None => (0..data.cfg_items.len()).collect(),
+    };
+    let mut findings = Vec::new();
+    let mut failures = 0;
+    for i in strokes {
+        let stroke = match parse_stroke(data, i, options) {
+            Ok(stroke) => stroke,
+            Err(e) => {
+                failures += 1;
+                println!("{}", e);
+                continue;
+            }
+        };
+        for finding in lint::lint(&stroke) {
+            let description = data.cfg_items[i].description.trim();
+            println!("{} (line {}): {}", description, data.line_label(i), finding);
+            findings.push(finding);
+        }
+    }
+    if findings.is_empty() {
+        println!("no problems found");
+    } else {
+        println!("{}", lint::summary(&findings));
+    }
+    Ok(failures == 0 && findings.iter().all(|f| f.severity < Severity::Error))
+}
+
+/// reports every problem with the List.txt offsets, returning whether there were none
+fn audit(data: &CfgData) -> bool {
+    for error in &data.list_errors {
+        println!("{}", error);
+    }
+    let warnings: Vec<String> = data
+        .list_warnings
+        .iter()
+        .map(|w| w.to_string())
+        .chain(data.location_warnings.iter().cloned())
+        .collect();
+    for warning in &warnings {
+        println!("warning: {}", warning);
+    }
+    data.list_errors.is_empty() && warnings.is_empty()
+}
+
+fn rules() {
+    for rule in RULES {
+        println!("{:<26} {:<8} {}", rule.id, rule.severity.to_string(), rule.description);
+    }
+}
+
+/// compares two cfg files using the same List.txt, returning whether every stroke could be compared
+fn diff(files: &Files, old: &str, new: &str, options: &Options) -> anyhow::Result<bool> {
+    let diff = diff_cfg_data(&load_other_cfg_data(files, old)?, &load_other_cfg_data(files, new)?);
+    let output = if options.json {
+        serde_json::to_string_pretty(&diff)? + "\n"
+    } else if diff.is_empty() {
+        "no differences\n".to_string()
+    } else {
+        diff.to_string()
+    };
+    write_output(options, output.as_bytes())?;
+    Ok(diff.errors.is_empty())
+}
+
+/// sets a field of one stroke and writes the file back, or to `--output`
+fn set(
+    files: &Files,
+    data: &CfgData,
+    name_or_index: &str,
+    field: &str,
+    value: &str,
+    options: &Options,
+) -> anyhow::Result<()> {
+    let i = find_stroke(data, name_or_index)?;
+    let path: FieldPath = field.parse()?;
+    data.parse_stroke_tolerant(i).map_err(|e| anyhow!("{}", describe_error(data, i, &e)))?;
+    let edit = data.set_value(i, &path, value)?;
+    let stroke = data.cfg_items[i].description.trim().to_string();
+    match edit.change {
+        Some(change) => {
+            // the header doesn't move, so only the span of the stroke changes
+            let block = edit.block;
+            let located = data.stroke_locations[i].map(|s| LocatedStroke { span: block, ..s });
+            if let Some(warning) = located.and_then(|s| over_budget(&data.cfg_items[i], &s)) {
+                if !options.force {
+                    bail!("{}, so nothing was written; use --force to write it anyway", warning);
+                }
+                println!("warning: {}", warning);
+            }
+            let path = options.output.as_ref().unwrap_or(&files.cfg_file);
+            fs::write(path, &edit.bytes).with_context(|| format!("could not write {}", path))?;
+            print!("{}", StrokeDiff { stroke, changes: vec![change] });
+        }
+        None => println!("{} / {} is already {}", stroke, path, value.trim()),
+    }
+    Ok(())
+}
@@ -1,27 +1,236 @@
-use crate::Files;
-use anyhow::Context;
+use crate::document::{snippet, CfgDocument, Location};
+use crate::edit::{set_value, Edit, FieldPath};
+use crate::lint::{lint, Finding};
+use crate::list_file::{audit, read_list_file, Category, ListEntry, ListError, ListWarning};
+use crate::offsets::{locate_strokes, over_budget, Calibration, LocatedStroke};
+use crate::sections::{locate_sections, Section};
+use crate::strokes::Stroke;
+use crate::tolerant;
+use anyhow::{anyhow, bail, Context};
+use serde_derive::Deserialize;
+use std::borrow::Cow;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{BufReader, Read};
+
+#[derive(Deserialize)]
+pub struct Files {
+    pub cfg_file: String,
+    pub list_file: String,
+    /// a cfg file whose strokes the preview outlines over the ones being edited
+    #[serde(default)]
+    pub reference_cfg_file: Option<String>,
+}
+
+/// loads the default file paths from stoke_preview.toml or returns empty strings if that file does not exist
+pub fn load_default_files() -> anyhow::Result<Files> {
+    match File::open("stroke_preview.toml") {
+        Ok(mut file) => {
+            let mut string =
+                String::with_capacity(file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0));
+            file.read_to_string(&mut string)?;
+            Ok(toml::from_str(&string)?)
+        }
+        Err(_) => Ok(Files {
+            cfg_file: "AI.cfg".to_string(),
+            list_file: "List.txt".to_string(),
+            reference_cfg_file: None,
+        }),
+    }
+}
 
 pub struct CfgData {
-    pub cfg_items: Vec<(String, i64)>,
+    /// the stroke entries of List.txt, sorted by offset
+    pub cfg_items: Vec<ListEntry>,
     pub cfg_contents: Vec<u8>,
+    /// the `StrokeAttributes` block found for each of `cfg_items`
+    pub stroke_locations: Vec<Option<LocatedStroke>>,
+    /// List.txt lines that could not be read and were skipped
+    pub list_errors: Vec<ListError>,
+    /// offsets in the whole of List.txt that clash, see `list_file::audit`
+    pub list_warnings: Vec<ListWarning>,
+    /// entries whose offset doesn't agree with the strokes found in the cfg file
+    pub location_warnings: Vec<String>,
+    /// how List.txt offsets were found to map onto this cfg file
+    pub calibration: Calibration,
+    /// every List.txt entry, then every block of the cfg file no entry points at
+    pub sections: Vec<Section>,
+    /// the lint findings for each of `cfg_items`, parsed tolerantly; empty if it can't be parsed
+    pub lint_findings: Vec<Vec<Finding>>,
+}
+
+/// Why the stroke for an entry could not be read, and where in the cfg file
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeError {
+    /// `None` if there was no stroke to parse
+    pub location: Option<Location>,
+    pub message: String,
+    /// the offending line with a caret under the problem, see `document::snippet`
+    pub snippet: Option<String>,
+}
+
+impl fmt::Display for StrokeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for StrokeError {}
+
+/// A deviation from the canonical layout that tolerant parsing accepted
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeWarning {
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for StrokeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl CfgData {
+    /// the line of the `StrokeAttributes` header for the `i`th entry, if it was found
+    pub fn line_number_of(&self, i: usize) -> Option<usize> {
+        self.stroke_locations[i].map(|s| s.line_number)
+    }
+
+    /// the line number of the `i`th entry for labels, or `?` if its stroke wasn't found
+    pub fn line_label(&self, i: usize) -> String {
+        self.line_number_of(i).map_or_else(|| "?".to_string(), |l| l.to_string())
+    }
+
+    /// the first entry with this description, ignoring surrounding whitespace
+    pub fn position_of(&self, description: &str) -> Option<usize> {
+        self.cfg_items.iter().position(|e| e.description.trim() == description.trim())
+    }
+
+    /// parses the stroke for the `i`th entry in `cfg_items`
+    pub fn parse_stroke(&self, i: usize) -> Result<Stroke, StrokeError> {
+        let (start, bytes) = self.stroke_bytes(i)?;
+        Stroke::parse(bytes).map_err(|e| self.error_at(start + e.offset, e.message))
+    }
+
+    /// parses the stroke for the `i`th entry with `tolerant::parse_stroke`, which accepts hand
+    /// edited layouts and warns about them
+    pub fn parse_stroke_tolerant(
+        &self,
+        i: usize,
+    ) -> Result<(Stroke, Vec<StrokeWarning>), StrokeError> {
+        let (start, bytes) = self.stroke_bytes(i)?;
+        let (stroke, warnings) = tolerant::parse_stroke(bytes)
+            .map_err(|e| self.error_at(start + e.offset, e.message))?;
+        let warnings = warnings
+            .into_iter()
+            .map(|w| StrokeWarning {
+                location: Location::of(&self.cfg_contents, start + w.offset),
+                message: w.message,
+            })
+            .collect();
+        Ok((stroke, warnings))
+    }
+
+    /// the `list_warnings` that involve the `i`th entry
+    pub fn list_warnings_of(&self, i: usize) -> impl Iterator<Item = &ListWarning> {
+        let line_number = self.cfg_items[i].line_number;
+        self.list_warnings.iter().filter(move |w| w.line_numbers.contains(&line_number))
+    }
+
+    /// a warning if the stroke for the `i`th entry has grown too long for its slot in the .gob
+    /// file, see `offsets::over_budget`
+    pub fn budget_warning(&self, i: usize) -> Option<String> {
+        over_budget(&self.cfg_items[i], self.stroke_locations[i].as_ref()?)
+    }
+
+    /// sets one field of the stroke for the `i`th entry, see `edit::set_value`
+    pub fn set_value(&self, i: usize, path: &FieldPath, value: &str) -> anyhow::Result<Edit> {
+        let located = self.stroke_locations[i].ok_or_else(|| {
+            anyhow!("no StrokeAttributes block found for {}", self.cfg_items[i].description.trim())
+        })?;
+        set_value(&self.cfg_contents, located.span, path, value)
+    }
+
+    /// the text of the `i`th of `sections` as it is in the cfg file, or why there is none
+    pub fn section_text(&self, i: usize) -> anyhow::Result<Cow<'_, str>> {
+        let section = &self.sections[i];
+        let entry = match (section.block, &section.entry) {
+            (Some(block), _) => {
+                return Ok(String::from_utf8_lossy(&self.cfg_contents[block.range()]))
+            }
+            (None, Some(entry)) => entry,
+            (None, None) => bail!("{} has no block", section),
+        };
+        let description = entry.description.trim();
+        match entry.category {
+            Category::Stroke => bail!("no StrokeAttributes block found for {}", description),
+            c if c.is_screen() => {
+                bail!("{} isn't in the cfg file, which has no screens", description)
+            }
+            _ => bail!(
+                "{} has no block: the cfg file has fewer blocks before the strokes than there are \
+                 offsets for them in List.txt",
+                description
+            ),
+        }
+    }
+
+    /// the block for the `i`th entry and where it starts
+    fn stroke_bytes(&self, i: usize) -> Result<(usize, &[u8]), StrokeError> {
+        let span = self.stroke_locations[i].map(|s| s.span).ok_or_else(|| StrokeError {
+            location: None,
+            snippet: None,
+            message: format!(
+                "no StrokeAttributes block found for {}",
+                self.cfg_items[i].description.trim()
+            ),
+        })?;
+        // the parser wants to see the NUL that ends the block
+        let end = (span.end + 1).min(self.cfg_contents.len());
+        Ok((span.start, &self.cfg_contents[span.start..end]))
+    }
+
+    fn error_at(&self, offset: usize, message: String) -> StrokeError {
+        StrokeError {
+            location: Some(Location::of(&self.cfg_contents, offset)),
+            message,
+            snippet: Some(snippet(&self.cfg_contents, offset)),
+        }
+    }
 }
 
 pub fn load_cfg_data(files: &Files) -> anyhow::Result<CfgData> {
-    let mut cfg_items: Vec<_> = read_list_file(&files.list_file)?
-        .map(|s| {
-            let parts = s.split(",").collect::<Vec<_>>();
-            (parts[0].to_string(), parts[1].parse::<i64>().unwrap())
-        })
-        // https://www.planetcricket.org/forums/threads/config-editor-v3.8697/post-130389
-        .filter(|(_, offset)| (558891008..=559079424).contains(offset))
-        .collect();
-    cfg_items.sort_by_key(|(_, offset)| *offset);
+    let list = read_list_file(&files.list_file)?;
+    let mut list_warnings = audit(&list.entries);
+    let mut cfg_items: Vec<_> =
+        list.entries.iter().filter(|e| e.category == Category::Stroke).cloned().collect();
+    cfg_items.sort_by_key(|e| e.offset);
 
     let cfg_contents = read_strokes_from_ai_cfg_file(&files.cfg_file)?;
+    let document = CfgDocument::parse(&cfg_contents);
+    let mut locations = locate_strokes(&document, &cfg_items);
+    list_warnings.append(&mut locations.mid_block);
+    let sections = locate_sections(&document, &list.entries, &cfg_items, &locations.strokes);
 
-    Ok(CfgData { cfg_items, cfg_contents })
+    let mut data = CfgData {
+        cfg_items,
+        cfg_contents,
+        stroke_locations: locations.strokes,
+        list_errors: list.errors,
+        list_warnings,
+        location_warnings: locations.warnings,
+        calibration: locations.calibration,
+        sections,
+        lint_findings: Vec::new(),
+    };
+    // linting every stroke is slow enough to notice, so do it once rather than on every redraw
+    data.lint_findings = (0..data.cfg_items.len())
+        .map(|i| data.parse_stroke_tolerant(i).map_or_else(|_| Vec::new(), |(s, _)| lint(&s)))
+        .collect();
+    Ok(data)
 }
 
 fn read_strokes_from_ai_cfg_file(path: &str) -> anyhow::Result<Vec<u8>> {
@@ -32,13 +241,3 @@ fn read_strokes_from_ai_cfg_file(path: &str) -> anyhow::Result<Vec<u8>> {
     buf.read_to_end(&mut destination)?;
     Ok(destination)
 }
-
-fn read_list_file(path: &str) -> anyhow::Result<impl Iterator<Item = String>> {
-    let file = File::open(path).context("could not open list file")?;
-    let buf = BufReader::new(file);
-    Ok(buf
-        .lines()
-        .enumerate()
-        .map(|(i, l)| l.unwrap_or_else(|e| panic!("error parsing line {}: {}", i, e)))
-        .filter(|line| !line.starts_with("//")))
-}
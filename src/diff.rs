@@ -0,0 +1,284 @@
+//! Field by field comparison of the strokes in two cfg files, matched by their List.txt
+//! description. Strokes are compared after parsing, so layout and whitespace don't count.
+
+use crate::data::CfgData;
+use crate::geometry::DIRECTION_UNITS_PER_TURN;
+use crate::strokes::{Stroke, StrokeTiming};
+use serde_derive::Serialize;
+use std::fmt;
+
+/// A field value as it appears in the cfg file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Number(i64),
+    Text(String),
+    /// an optional key that isn't there, like `EdgeModifier6`
+    Missing,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Text(t) => f.write_str(t),
+            Value::Missing => f.write_str("(none)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// e.g. `Normal KF2`, or `None` for the fields before the keyframes
+    pub keyframe: Option<String>,
+    pub field: &'static str,
+    pub old: Value,
+    pub new: Value,
+    /// the change in degrees on the pitch: how far the pie turns for `Direction`, and for
+    /// `DirectionArea` how much wider the whole pie gets. `DirectionArea` is the width on each
+    /// side of the direction, so that is twice the turn its change alone would give.
+    pub degrees: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrokeDiff {
+    pub stroke: String,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CfgDiff {
+    /// strokes with at least one changed field, in List.txt order
+    pub changed: Vec<StrokeDiff>,
+    pub only_in_old: Vec<String>,
+    pub only_in_new: Vec<String>,
+    /// strokes that could not be compared because one side failed to parse
+    pub errors: Vec<String>,
+}
+
+impl CfgDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+            && self.only_in_old.is_empty()
+            && self.only_in_new.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for StrokeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            write!(f, "{} / ", self.stroke)?;
+            if let Some(keyframe) = &change.keyframe {
+                write!(f, "{} / ", keyframe)?;
+            }
+            write!(f, "{} {} \u{2192} {}", change.field, change.old, change.new)?;
+            if let Some(degrees) = change.degrees {
+                write!(f, ", {:+.1}\u{b0}", degrees)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CfgDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stroke in &self.changed {
+            write!(f, "{}", stroke)?;
+        }
+        for name in &self.only_in_old {
+            writeln!(f, "only in old: {}", name)?;
+        }
+        for name in &self.only_in_new {
+            writeln!(f, "only in new: {}", name)?;
+        }
+        for error in &self.errors {
+            writeln!(f, "error: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// the fields before the keyframes, in the order Config Editor writes them
+fn header_fields(stroke: &Stroke) -> Vec<(&'static str, Value)> {
+    let n = Value::Number;
+    vec![
+        ("Type", Value::Text(stroke.stroke_type.to_string())),
+        ("EdgeModifier", n(stroke.edge_modifier)),
+        ("EdgeModifier6", stroke.edge_modifier6.map_or(Value::Missing, n)),
+        ("Difficulty", n(stroke.difficulty)),
+        ("Reward", n(stroke.reward)),
+        ("Difficulty6", n(stroke.difficulty6)),
+        ("Reward6", n(stroke.reward6)),
+        ("EdgeProb", n(stroke.edge_prob)),
+        ("EdgeProb6", n(stroke.edge_prob6)),
+        ("BowlerTypes", n(stroke.bowler_types)),
+        ("BallStumps", n(stroke.ball_stumps)),
+        ("BallBatsman", n(stroke.ball_batsman)),
+        ("BallLength", n(stroke.ball_length)),
+    ]
+}
+
+fn keyframe_fields(t: &StrokeTiming) -> Vec<(&'static str, i64)> {
+    vec![
+        ("Frame", t.frame),
+        ("Vertical", t.vertical),
+        ("Direction", t.direction),
+        ("DirectionArea", t.direction_area),
+        ("Power", t.power),
+        ("PowerArea", t.power_area),
+    ]
+}
+
+/// how changes name a keyframe, e.g. `Normal KF2`
+pub fn keyframe_label(mode: &str, keyframe: usize) -> String {
+    format!("{} KF{}", mode, keyframe)
+}
+
+/// every field that differs between `old` and `new`
+pub fn diff_strokes(old: &Stroke, new: &Stroke) -> Vec<FieldChange> {
+    let mut changes: Vec<FieldChange> = header_fields(old)
+        .into_iter()
+        .zip(header_fields(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| FieldChange {
+            keyframe: None,
+            field,
+            old,
+            new,
+            degrees: None,
+        })
+        .collect();
+    let modes = [
+        ("Normal", &old.timings_normal, &new.timings_normal),
+        ("6Hit", &old.timings_6hit, &new.timings_6hit),
+    ];
+    for (mode, old_timings, new_timings) in modes.iter() {
+        for (i, (old, new)) in old_timings.iter().zip(new_timings.iter()).enumerate() {
+            let fields = keyframe_fields(old).into_iter().zip(keyframe_fields(new));
+            for ((field, old), (_, new)) in fields.filter(|(old, new)| old != new) {
+                let turn = (new as f64 - old as f64) / DIRECTION_UNITS_PER_TURN * 360.0;
+                let degrees = match field {
+                    "Direction" => Some(turn),
+                    "DirectionArea" => Some(2.0 * turn),
+                    _ => None,
+                };
+                changes.push(FieldChange {
+                    keyframe: Some(keyframe_label(mode, i)),
+                    field,
+                    old: Value::Number(old),
+                    new: Value::Number(new),
+                    degrees,
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Compares every stroke in `old` with the stroke of the same description in `new`. Both are
+/// parsed tolerantly, so hand edited files can be compared with Config Editor exports.
+pub fn diff_cfg_data(old: &CfgData, new: &CfgData) -> CfgDiff {
+    let mut diff = CfgDiff::default();
+    let mut unmatched: Vec<usize> = (0..new.cfg_items.len()).collect();
+    for (i, entry) in old.cfg_items.iter().enumerate() {
+        let name = entry.description.trim();
+        // descriptions can repeat, so each one matches the first of its namesakes left over
+        let j = match unmatched.iter().position(|&j| new.cfg_items[j].description.trim() == name) {
+            Some(position) => unmatched.remove(position),
+            None => {
+                diff.only_in_old.push(name.to_string());
+                continue;
+            }
+        };
+        match (old.parse_stroke_tolerant(i), new.parse_stroke_tolerant(j)) {
+            (Ok((old, _)), Ok((new, _))) => {
+                let changes = diff_strokes(&old, &new);
+                if !changes.is_empty() {
+                    diff.changed.push(StrokeDiff { stroke: name.to_string(), changes });
+                }
+            }
+            (Err(e), _) => diff.errors.push(format!("{} in old: {}", name, e)),
+            (_, Err(e)) => diff.errors.push(format!("{} in new: {}", name, e)),
+        }
+    }
+    diff.only_in_new =
+        unmatched.iter().map(|&j| new.cfg_items[j].description.trim().to_string()).collect();
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_fixtures::{stroke_text, HEADER, KEYFRAME};
+    use crate::tolerant::parse_stroke;
+
+    /// the fixture stroke with `header` and `keyframe`, laid out with `line_ending`
+    fn stroke(header: &str, keyframe: &str, line_ending: &str) -> Stroke {
+        parse_stroke(stroke_text(header, keyframe).replace("\r\n", line_ending).as_bytes())
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn ignores_layout_differences() {
+        let old = stroke(HEADER, KEYFRAME, "\r\n");
+        let new = stroke(&HEADER.replace(' ', "  "), &KEYFRAME.replace(' ', "\t"), "\n");
+        assert_eq!(diff_strokes(&old, &new), []);
+    }
+
+    #[test]
+    fn reports_changed_fields_with_the_angle_they_turn_or_widen() {
+        let old = stroke(HEADER, KEYFRAME, "\r\n");
+        let header = HEADER
+            .replace("Attacking", "Defensive")
+            .replace("EdgeModifier 10", "EdgeModifier 10\nEdgeModifier6 12");
+        let new = stroke(
+            &header,
+            &KEYFRAME.replace("DirectionArea 24298037", "DirectionArea 30000000"),
+            "\r\n",
+        );
+        let changes = diff_strokes(&old, &new);
+        assert_eq!(changes.len(), 12);
+        assert_eq!(changes[0].new, Value::Text("Defensive".to_string()));
+        assert_eq!((&changes[1].old, &changes[1].new), (&Value::Missing, &Value::Number(12)));
+        let diff = StrokeDiff { stroke: "Cover drive attack".to_string(), changes };
+        let text = diff.to_string();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[0], "Cover drive attack / Type Attacking \u{2192} Defensive");
+        assert_eq!(lines[1], "Cover drive attack / EdgeModifier6 (none) \u{2192} 12");
+        assert_eq!(
+            lines[4],
+            "Cover drive attack / Normal KF2 / DirectionArea 24298037 \u{2192} 30000000, +15.3\u{b0}"
+        );
+        assert_eq!(
+            lines[11],
+            "Cover drive attack / 6Hit KF4 / DirectionArea 24298037 \u{2192} 30000000, +15.3\u{b0}"
+        );
+    }
+
+    #[test]
+    fn serialises_values_as_json_numbers_and_strings() {
+        let old = stroke(HEADER, KEYFRAME, "\r\n");
+        let new = stroke(HEADER, &KEYFRAME.replace("Power 144631", "Power 200000"), "\r\n");
+        let diff = CfgDiff {
+            changed: vec![StrokeDiff {
+                stroke: "Pull".to_string(),
+                changes: diff_strokes(&old, &new).into_iter().take(1).collect(),
+            }],
+            only_in_new: vec!["Hook 3".to_string()],
+            ..CfgDiff::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&diff).unwrap(),
+            "{\"changed\":[{\"stroke\":\"Pull\",\"changes\":[{\"keyframe\":\"Normal KF0\",\
+             \"field\":\"Power\",\"old\":144631,\"new\":200000,\"degrees\":null}]}],\
+             \"only_in_old\":[],\"only_in_new\":[\"Hook 3\"],\"errors\":[]}"
+        );
+        assert_eq!(
+            diff.to_string(),
+            "Pull / Normal KF0 / Power 144631 \u{2192} 200000\nonly in new: Hook 3\n"
+        );
+    }
+}
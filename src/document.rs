@@ -0,0 +1,419 @@
+//! A lossless model of a whole exported AI.cfg file.
+//!
+//! Every byte of the file belongs to exactly one token, so the document can be written back out
+//! unchanged. Blocks, lines and key/value entries only record byte spans into the source, which
+//! lets callers rewrite a single value in place and point error messages at exact positions.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+/// A half-open byte range into the source of a `CfgDocument`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn range(self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn len(self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// a run of NUL bytes, which pad out and separate blocks
+    Nul,
+    /// `\n`, `\r\n` or a lone `\r`
+    LineEnding,
+    /// spaces and tabs
+    Whitespace,
+    /// `//` up to the end of the line
+    Comment,
+    /// anything else: a block header, a key or a value
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// A key and the value following it on the same line, e.g. `DirectionArea 24298037`.
+/// A word on its own at the end of a line, such as a block header, has no value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: Span,
+    pub value: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// the whole line, excluding its line ending
+    pub span: Span,
+    pub entries: Vec<Entry>,
+}
+
+/// A run of text between NUL separators
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub span: Span,
+    pub lines: Vec<Line>,
+}
+
+impl Block {
+    /// the first word in the block, e.g. `StrokeAttributes`
+    pub fn header(&self) -> Option<Span> {
+        self.entries().next().map(|e| e.key)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines.iter().flat_map(|l| l.entries.iter())
+    }
+}
+
+/// A 1-based line and byte column in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// the location of `offset` in `source`, for when there is no parsed document to ask
+    pub fn of(source: &[u8], offset: usize) -> Location {
+        Location::in_lines(&line_starts(source), offset)
+    }
+
+    /// the location of `offset` given the offset at which each line starts
+    fn in_lines(line_starts: &[usize], offset: usize) -> Location {
+        let line_index = match line_starts.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        Location { line: line_index + 1, column: offset - line_starts[line_index] + 1 }
+    }
+}
+
+/// the offset at which each line of `source` starts, ending lines at `\r\n`, `\n` or a lone `\r`
+/// just as the tokenizer does
+fn line_starts(source: &[u8]) -> Vec<usize> {
+    let ends_line = |i: usize| match source[i] {
+        b'\n' => true,
+        b'\r' => source.get(i + 1) != Some(&b'\n'),
+        _ => false,
+    };
+    std::iter::once(0).chain((0..source.len()).filter(|&i| ends_line(i)).map(|i| i + 1)).collect()
+}
+
+/// A compiler-style excerpt of the line containing `offset`, with a caret under that byte:
+///
+/// ```text
+/// 493 | BallStump 3
+///     | ^
+/// ```
+pub fn snippet(source: &[u8], offset: usize) -> String {
+    let location = Location::of(source, offset);
+    let start = offset + 1 - location.column;
+    let end = source[start..]
+        .iter()
+        .position(|&c| c == b'\r' || c == b'\n' || c == 0)
+        .map_or(source.len(), |i| start + i);
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).replace('\t', " ");
+    let number = location.line.to_string();
+    format!(
+        "{} | {}\n{} | {}^",
+        number,
+        text(&source[start..end]),
+        " ".repeat(number.len()),
+        " ".repeat(text(&source[start..offset.min(end)]).chars().count())
+    )
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+pub struct CfgDocument<'a> {
+    source: &'a [u8],
+    tokens: Vec<Token>,
+    blocks: Vec<Block>,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> CfgDocument<'a> {
+    /// Parses a cfg file. This cannot fail: bytes that mean nothing to us are kept as words.
+    pub fn parse(source: &'a [u8]) -> CfgDocument<'a> {
+        let tokens = tokenize(source);
+        let blocks = group_blocks(&tokens);
+        CfgDocument { source, tokens, blocks, line_starts: line_starts(source) }
+    }
+
+    pub fn source(&self) -> &'a [u8] {
+        self.source
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn text(&self, span: Span) -> &'a [u8] {
+        &self.source[span.range()]
+    }
+
+    pub fn text_lossy(&self, span: Span) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.text(span))
+    }
+
+    /// blocks whose header is `StrokeAttributes`, possibly preceded by a single prefix byte
+    pub fn stroke_blocks(&self) -> impl Iterator<Item = &Block> + '_ {
+        self.blocks
+            .iter()
+            .filter(move |b| matches!(b.header(), Some(h) if self.is_stroke_header(h)))
+    }
+
+    fn is_stroke_header(&self, header: Span) -> bool {
+        let marker = b"StrokeAttributes";
+        let header = self.text(header);
+        (header.len() == marker.len() || header.len() == marker.len() + 1)
+            && header[header.len() - marker.len()..].eq_ignore_ascii_case(marker)
+    }
+
+    /// the line and column of a byte offset into the source
+    pub fn location(&self, offset: usize) -> Location {
+        Location::in_lines(&self.line_starts, offset)
+    }
+
+    /// the source text of the given 1-based line, without its line ending
+    pub fn line_text(&self, line: usize) -> &'a [u8] {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).map_or(self.source.len(), |&next| next - 1);
+        let text = &self.source[start..end];
+        text.strip_suffix(b"\r").unwrap_or(text)
+    }
+
+    /// reassembles the file from its tokens, which always gives back the original bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.tokens.iter().flat_map(|t| self.text(t.span).iter().copied()).collect()
+    }
+
+    /// a copy of the file with the bytes in `span` replaced and everything else left alone
+    pub fn replace(&self, span: Span, replacement: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.source.len() + replacement.len());
+        bytes.extend_from_slice(&self.source[..span.start]);
+        bytes.extend_from_slice(replacement);
+        bytes.extend_from_slice(&self.source[span.end..]);
+        bytes
+    }
+}
+
+fn tokenize(b: &[u8]) -> Vec<Token> {
+    let skip_while = |mut i: usize, f: &dyn Fn(u8) -> bool| {
+        while i < b.len() && f(b[i]) {
+            i += 1;
+        }
+        i
+    };
+    let starts_comment = |i: usize| b[i] == b'/' && b.get(i + 1) == Some(&b'/');
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < b.len() {
+        let start = i;
+        let kind = match b[i] {
+            0 => {
+                i = skip_while(i, &|c| c == 0);
+                TokenKind::Nul
+            }
+            b'\r' if b.get(i + 1) == Some(&b'\n') => {
+                i += 2;
+                TokenKind::LineEnding
+            }
+            b'\r' | b'\n' => {
+                i += 1;
+                TokenKind::LineEnding
+            }
+            b' ' | b'\t' => {
+                i = skip_while(i, &|c| c == b' ' || c == b'\t');
+                TokenKind::Whitespace
+            }
+            _ if starts_comment(i) => {
+                i = skip_while(i, &|c| c != 0 && c != b'\r' && c != b'\n');
+                TokenKind::Comment
+            }
+            _ => {
+                while i < b.len() && !b" \t\r\n\0".contains(&b[i]) && !starts_comment(i) {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+        };
+        tokens.push(Token { kind, span: Span { start, end: i } });
+    }
+    tokens
+}
+
+fn group_blocks(tokens: &[Token]) -> Vec<Block> {
+    struct Builder {
+        blocks: Vec<Block>,
+        block: Option<Block>,
+        line: Option<(usize, usize, Vec<Span>)>,
+    }
+
+    impl Builder {
+        fn end_line(&mut self) {
+            if let Some((start, end, words)) = self.line.take() {
+                let entries = words
+                    .chunks(2)
+                    .map(|pair| Entry { key: pair[0], value: pair.get(1).copied() })
+                    .collect();
+                let block = self.block.as_mut().expect("lines are always inside a block");
+                block.lines.push(Line { span: Span { start, end }, entries });
+            }
+        }
+
+        fn end_block(&mut self) {
+            self.end_line();
+            self.blocks.extend(self.block.take());
+        }
+    }
+
+    let mut builder = Builder { blocks: Vec::new(), block: None, line: None };
+    for token in tokens {
+        if token.kind == TokenKind::Nul {
+            builder.end_block();
+            continue;
+        }
+        let block = builder.block.get_or_insert(Block { span: token.span, lines: Vec::new() });
+        block.span.end = token.span.end;
+        if token.kind == TokenKind::LineEnding {
+            let start = token.span.start;
+            builder.line.get_or_insert((start, start, Vec::new()));
+            builder.end_line();
+            continue;
+        }
+        let line = builder.line.get_or_insert((token.span.start, token.span.start, Vec::new()));
+        line.1 = token.span.end;
+        if token.kind == TokenKind::Word {
+            line.2.push(token.span);
+        }
+    }
+    builder.end_block();
+    builder.blocks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CFG: &[u8] = b"BowlingTypes\r\nFast 1 // quick\r\n\0\0\0SStrokeAttributes\r\n\r\nType \
+        Defensive\r\nDifficulty 0  Reward 5\r\n\r\nMode Normal KeyFrame 0\r\n\0\0";
+
+    #[test]
+    fn keeps_every_byte() {
+        let document = CfgDocument::parse(CFG);
+        assert_eq!(document.to_bytes(), CFG);
+        let covered: usize = document.tokens().iter().map(|t| t.span.len()).sum();
+        assert_eq!(covered, CFG.len());
+    }
+
+    #[test]
+    fn splits_blocks_at_nul_separators() {
+        let document = CfgDocument::parse(CFG);
+        let headers: Vec<_> = document
+            .blocks()
+            .iter()
+            .map(|b| document.text_lossy(b.header().unwrap()).into_owned())
+            .collect();
+        assert_eq!(headers, ["BowlingTypes", "SStrokeAttributes"]);
+        assert_eq!(document.stroke_blocks().count(), 1);
+        assert_eq!(document.text(document.blocks()[0].lines[1].span), b"Fast 1 // quick");
+    }
+
+    #[test]
+    fn records_key_and_value_spans() {
+        let document = CfgDocument::parse(CFG);
+        let stroke = document.stroke_blocks().next().unwrap();
+        let entries: Vec<_> = stroke
+            .entries()
+            .map(|e| {
+                (
+                    document.text_lossy(e.key).into_owned(),
+                    e.value.map(|v| document.text_lossy(v).into_owned()),
+                )
+            })
+            .collect();
+        let expected = [
+            ("SStrokeAttributes", None),
+            ("Type", Some("Defensive")),
+            ("Difficulty", Some("0")),
+            ("Reward", Some("5")),
+            ("Mode", Some("Normal")),
+            ("KeyFrame", Some("0")),
+        ];
+        let expected: Vec<_> =
+            expected.iter().map(|(k, v)| (k.to_string(), v.map(|v| v.to_string()))).collect();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn replaces_a_value_without_touching_anything_else() {
+        let document = CfgDocument::parse(CFG);
+        let reward = document
+            .stroke_blocks()
+            .flat_map(|b| b.entries())
+            .find(|e| document.text(e.key) == b"Reward")
+            .unwrap();
+        let edited = document.replace(reward.value.unwrap(), b"-25");
+        let expected = String::from_utf8_lossy(CFG).replace("Reward 5", "Reward -25");
+        assert_eq!(String::from_utf8_lossy(&edited), expected);
+    }
+
+    #[test]
+    fn locates_offsets_by_line_and_column() {
+        let document = CfgDocument::parse(CFG);
+        let stroke = document.stroke_blocks().next().unwrap();
+        let difficulty = &stroke.lines[3];
+        assert_eq!(
+            document.location(difficulty.entries[1].key.start),
+            Location { line: 6, column: 15 }
+        );
+        assert_eq!(document.line_text(6), b"Difficulty 0  Reward 5");
+        assert_eq!(
+            Location::of(CFG, difficulty.entries[1].key.start),
+            Location { line: 6, column: 15 }
+        );
+        assert_eq!(
+            snippet(CFG, difficulty.entries[1].key.start),
+            "6 | Difficulty 0  Reward 5\n  |               ^"
+        );
+    }
+
+    #[test]
+    fn ends_lines_where_the_tokenizer_does() {
+        let source = b"Camera\rHeight 10\r\nAngle 5\nZoom 2";
+        let document = CfgDocument::parse(source);
+        let line_endings = document.tokens().iter().filter(|t| t.kind == TokenKind::LineEnding);
+        assert_eq!(line_endings.count(), 3);
+        assert_eq!(document.line_text(2), b"Height 10");
+        assert_eq!(document.line_text(3), b"Angle 5");
+        assert_eq!(document.location(26), Location { line: 4, column: 1 });
+        assert_eq!(Location::of(source, 17), Location { line: 2, column: 11 });
+    }
+}
@@ -0,0 +1,245 @@
+//! Changing a single value of a stroke in the cfg file, leaving every other byte where it was.
+
+use crate::diff::{diff_strokes, keyframe_label, FieldChange};
+use crate::document::Span;
+use crate::strokes::parse_number;
+use crate::tolerant::{self, HEADER_KEYS, KEYFRAME_KEYS, MODES};
+use anyhow::{anyhow, bail, Context};
+use std::fmt;
+use std::str::FromStr;
+
+/// A field of a stroke, written like `edge_prob` for the header or `normal.2.direction_area` and
+/// `6hit.0.power` for a keyframe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldPath {
+    /// the mode and keyframe, or `None` for the header
+    pub keyframe: Option<(&'static str, usize)>,
+    /// the key as it is written in the cfg file
+    pub key: &'static str,
+}
+
+/// `DirectionArea` as `direction_area`
+fn snake_case(key: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in key.char_indices() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+impl FromStr for FieldPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<FieldPath> {
+        let find_key = |keys: &[&'static str], name: &str| {
+            keys.iter().copied().find(|k| snake_case(k) == name.to_ascii_lowercase()).ok_or_else(
+                || {
+                    let names: Vec<_> = keys.iter().map(|k| snake_case(k)).collect();
+                    anyhow!("unknown field `{}`, expected one of {}", name, names.join(", "))
+                },
+            )
+        };
+        match s.split('.').collect::<Vec<_>>().as_slice() {
+            [key] => Ok(FieldPath { keyframe: None, key: find_key(&HEADER_KEYS, key)? }),
+            [mode, keyframe, key] => {
+                let mode =
+                    MODES.iter().copied().find(|m| m.eq_ignore_ascii_case(mode)).ok_or_else(
+                        || anyhow!("unknown mode `{}`, expected normal or 6hit", mode),
+                    )?;
+                let keyframe = keyframe.parse().ok().filter(|&k| k < 5).ok_or_else(|| {
+                    anyhow!("expected a keyframe from 0 to 4, not `{}`", keyframe)
+                })?;
+                Ok(FieldPath {
+                    keyframe: Some((mode, keyframe)),
+                    key: find_key(&KEYFRAME_KEYS, key)?,
+                })
+            }
+            _ => {
+                bail!("expected a field like `edge_prob` or `normal.2.direction_area`, not `{}`", s)
+            }
+        }
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.keyframe {
+            Some((mode, keyframe)) => write!(f, "Mode {} KeyFrame {} {}", mode, keyframe, self.key),
+            None => f.write_str(self.key),
+        }
+    }
+}
+
+/// The result of `set_value`
+#[derive(Debug)]
+pub struct Edit {
+    /// the whole file with the new value
+    pub bytes: Vec<u8>,
+    /// the stroke block in `bytes`, which is longer or shorter if the value is
+    pub block: Span,
+    /// `None` if the field already had the value
+    pub change: Option<FieldChange>,
+}
+
+/// Sets the field at `path` of the stroke `block` in `contents` to `value`. Only the value's bytes
+/// change: if the new value is longer or shorter, the NUL padding after the block shrinks or grows
+/// to match, so nothing after it moves. The stroke is parsed again afterwards to check that
+/// exactly that field changed.
+pub fn set_value(
+    contents: &[u8],
+    block: Span,
+    path: &FieldPath,
+    value: &str,
+) -> anyhow::Result<Edit> {
+    let value = value.trim();
+    let new_text = if path.key == "Type" {
+        if value.is_empty()
+            || value.contains(|c: char| c.is_whitespace() || c == '\0')
+            || value.contains("//")
+        {
+            bail!("a Type must be a single word, not {:?}", value);
+        }
+        value.to_string()
+    } else {
+        parse_number(value).with_context(|| format!("could not set {}", path))?.to_string()
+    };
+
+    // the parsers want to see the NUL that ends the block
+    let stroke_bytes =
+        |contents: &[u8], end: usize| contents[block.start..(end + 1).min(contents.len())].to_vec();
+    let (old_stroke, _) = tolerant::parse_stroke(&stroke_bytes(contents, block.end))?;
+    let value_span =
+        tolerant::value_span(&stroke_bytes(contents, block.end), path.keyframe, path.key)
+            .ok_or_else(|| {
+                anyhow!(
+                    "the stroke has no {} to change, so it would have to be added by hand",
+                    path
+                )
+            })?;
+    let value_span =
+        Span { start: block.start + value_span.start, end: block.start + value_span.end };
+
+    let growth = new_text.len() as i64 - value_span.len() as i64;
+    let padding = contents[block.end..].iter().take_while(|&&c| c == 0).count() as i64;
+    // keep at least one NUL to end the block, unless it ends the file
+    if padding > 0 && growth >= padding {
+        bail!(
+            "{} doesn't fit: the stroke only has {} bytes of padding after it",
+            new_text,
+            padding
+        );
+    }
+    let mut bytes = Vec::with_capacity(contents.len());
+    bytes.extend_from_slice(&contents[..value_span.start]);
+    bytes.extend_from_slice(new_text.as_bytes());
+    bytes.extend_from_slice(&contents[value_span.end..block.end]);
+    let new_end = bytes.len();
+    bytes.resize(bytes.len() + (padding - growth).max(0) as usize, 0);
+    bytes.extend_from_slice(&contents[block.end + padding as usize..]);
+
+    let (new_stroke, _) = tolerant::parse_stroke(&stroke_bytes(&bytes, new_end))
+        .context("the edited stroke no longer parses")?;
+    let changes = diff_strokes(&old_stroke, &new_stroke);
+    let keyframe = path.keyframe.map(|(mode, keyframe)| keyframe_label(mode, keyframe));
+    match changes.as_slice() {
+        // the same value written differently, like `+5` for `5`
+        [] => {}
+        [change]
+            if change.field == path.key
+                && change.keyframe == keyframe
+                && change.new.to_string() == new_text => {}
+        _ => bail!("setting {} to {} would change more than that field", path, new_text),
+    }
+    let block = Span { start: block.start, end: new_end };
+    Ok(Edit { bytes, block, change: changes.into_iter().next() })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diff::Value;
+    use crate::document::CfgDocument;
+    use crate::list_file::SIZE_UNIT;
+    use crate::test_fixtures::{stroke_text, HEADER, KEYFRAME};
+
+    /// a cfg file with the fixture stroke in a two unit slot after some other block
+    fn cfg() -> Vec<u8> {
+        let mut cfg = b"Camera\r\nHeight 10\r\n\0".to_vec();
+        let slot_start = cfg.len();
+        let keyframe = KEYFRAME.replace("DirectionArea 24298037", "DirectionArea 24298037 // wide");
+        cfg.extend_from_slice(stroke_text(HEADER, &keyframe).as_bytes());
+        cfg.resize(slot_start + 2 * SIZE_UNIT as usize, 0);
+        cfg.extend_from_slice(b"SStrokeAttributes\r\n");
+        cfg
+    }
+
+    fn block(cfg: &[u8]) -> Span {
+        CfgDocument::parse(cfg).stroke_blocks().next().unwrap().span
+    }
+
+    #[test]
+    fn reads_field_paths() {
+        let path: FieldPath = "normal.2.direction_area".parse().unwrap();
+        assert_eq!(path, FieldPath { keyframe: Some(("Normal", 2)), key: "DirectionArea" });
+        assert_eq!(path.to_string(), "Mode Normal KeyFrame 2 DirectionArea");
+        assert_eq!("6HIT.4.Power".parse::<FieldPath>().unwrap().keyframe, Some(("6Hit", 4)));
+        assert_eq!("edge_prob6".parse::<FieldPath>().unwrap().key, "EdgeProb6");
+        assert!("normal.5.power".parse::<FieldPath>().is_err());
+        let error = "edge_probability".parse::<FieldPath>().unwrap_err().to_string();
+        assert!(error.starts_with("unknown field `edge_probability`, expected one of type, "));
+    }
+
+    #[test]
+    fn rewrites_only_the_value_and_keeps_the_slot_size() {
+        let cfg = cfg();
+        let path = "normal.2.direction_area".parse().unwrap();
+        let edit = set_value(&cfg, block(&cfg), &path, "300000000").unwrap();
+        assert_eq!(edit.bytes.len(), cfg.len());
+        let text = String::from_utf8_lossy(&edit.bytes);
+        assert_eq!(text.matches("DirectionArea 300000000 // wide").count(), 1);
+        assert_eq!(text.matches("DirectionArea 24298037 // wide").count(), 9);
+        assert!(edit.bytes.ends_with(b"\0SStrokeAttributes\r\n"));
+        let change = edit.change.unwrap();
+        assert_eq!(change.new, Value::Number(300000000));
+
+        let path = "type".parse().unwrap();
+        let edit = set_value(&cfg, block(&cfg), &path, "Leave").unwrap();
+        assert_eq!(edit.bytes.len(), cfg.len());
+        assert!(String::from_utf8_lossy(&edit.bytes).contains("\r\nType Leave\r\n"));
+    }
+
+    #[test]
+    fn refuses_values_that_are_invalid_missing_or_too_long() {
+        let cfg = cfg();
+        let set = |path: &str, value: &str| {
+            set_value(&cfg, block(&cfg), &path.parse().unwrap(), value)
+                .map_err(|e| format!("{:#}", e))
+        };
+        assert_eq!(
+            set("6hit.0.power", "lots").unwrap_err(),
+            "could not set Mode 6Hit KeyFrame 0 Power: `lots` is not a whole number"
+        );
+        assert_eq!(
+            set("edge_modifier6", "5").unwrap_err(),
+            "the stroke has no EdgeModifier6 to change, so it would have to be added by hand"
+        );
+        assert!(set("type", "Very attacking").is_err());
+        assert!(set("edge_prob", "+0").unwrap().change.is_none());
+
+        let mut full = cfg.clone();
+        let end = block(&full).end;
+        full.drain(end + 3..full.len() - "SStrokeAttributes\r\n".len());
+        let set =
+            |value: &str| set_value(&full, block(&full), &"edge_prob".parse().unwrap(), value);
+        let edit = set("123").unwrap();
+        assert_eq!(edit.bytes.len(), full.len());
+        assert_eq!(edit.block.len(), block(&full).len() + 2);
+        assert_eq!(
+            set("1234").unwrap_err().to_string(),
+            "1234 doesn't fit: the stroke only has 3 bytes of padding after it"
+        );
+    }
+}
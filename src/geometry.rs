@@ -0,0 +1,201 @@
+//! The shapes in the pitch diagram, independent of how they are drawn. The preview window, the
+//! SVG renderer and anything else that draws strokes all work from these.
+
+use crate::strokes::StrokeTiming;
+use std::f64::consts::TAU;
+
+/// direction units in one full turn
+pub const DIRECTION_UNITS_PER_TURN: f64 = 269_070_000.0;
+/// the direction pointing directly behind the batsman
+pub const DIRECTION_BEHIND_BATSMAN: f64 = 60_000.0;
+/// the power drawn at the edge of the pitch circle
+pub const POWER_AT_PITCH_EDGE: f64 = 4_500_000.0;
+
+/// space in pixels between the pitch circle and the edge of a diagram
+pub const PITCH_PADDING: f64 = 5.0;
+/// diagrams in each row of a contact sheet, which fits the five timings of a mode on one row
+pub const SHEET_COLUMNS: usize = 5;
+
+/// a colour as red, green and blue
+pub type Rgb = [u8; 3];
+
+/// the pitch
+pub const GREEN: Rgb = [0, 190, 0];
+/// the timings that aren't selected
+pub const DARK_GREEN: Rgb = [0, 150, 0];
+/// the selected timing out to its minimum power
+pub const STROKE_MIN: Rgb = [250, 100, 50];
+/// the selected timing out to its maximum power
+pub const STROKE_MAX: Rgb = [250, 250, 30];
+/// the outline of a second version of the selected timing
+pub const OVERLAY: Rgb = [0, 60, 255];
+
+/// The pie drawn for one keyframe. Angles are in radians anticlockwise, 0 being directly behind
+/// the batsman. Radii are fractions of the radius of the pitch circle; the `_metres` methods scale
+/// them to a particular ground.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeShape {
+    pub centre_angle: f64,
+    pub min_angle: f64,
+    pub max_angle: f64,
+    /// radius of `power - power_area`, never negative
+    pub inner_radius: f64,
+    /// radius of `power + power_area`, never negative
+    pub outer_radius: f64,
+}
+
+impl StrokeShape {
+    pub fn of(timing: &StrokeTiming) -> StrokeShape {
+        let centre_angle =
+            (timing.direction_f64() - DIRECTION_BEHIND_BATSMAN) / DIRECTION_UNITS_PER_TURN * TAU;
+        let half_width = timing.direction_area_f64() / DIRECTION_UNITS_PER_TURN * TAU;
+        let (power, power_area) = (timing.power_f64(), timing.power_area_f64());
+        StrokeShape {
+            centre_angle,
+            min_angle: (centre_angle - half_width) % TAU,
+            max_angle: (centre_angle + half_width) % TAU,
+            inner_radius: ((power - power_area) / POWER_AT_PITCH_EDGE).max(0.0),
+            outer_radius: ((power + power_area) / POWER_AT_PITCH_EDGE).max(0.0),
+        }
+    }
+
+    pub fn centre_angle_degrees(&self) -> f64 {
+        self.centre_angle.to_degrees()
+    }
+
+    pub fn min_angle_degrees(&self) -> f64 {
+        self.min_angle.to_degrees()
+    }
+
+    pub fn max_angle_degrees(&self) -> f64 {
+        self.max_angle.to_degrees()
+    }
+
+    /// The angle swept anticlockwise from `min_angle` to `max_angle`. Like GDI's `Pie`, equal
+    /// start and end angles give a full circle.
+    pub fn sweep(&self) -> f64 {
+        let sweep = (self.max_angle - self.min_angle).rem_euclid(TAU);
+        if sweep < 1e-9 {
+            TAU
+        } else {
+            sweep
+        }
+    }
+
+    pub fn is_full_circle(&self) -> bool {
+        self.sweep() == TAU
+    }
+
+    /// `inner_radius` in metres, for a ground whose pitch circle has a radius of
+    /// `pitch_radius_metres`
+    pub fn inner_radius_metres(&self, pitch_radius_metres: f64) -> f64 {
+        self.inner_radius * pitch_radius_metres
+    }
+
+    /// `outer_radius` in metres, for a ground whose pitch circle has a radius of
+    /// `pitch_radius_metres`
+    pub fn outer_radius_metres(&self, pitch_radius_metres: f64) -> f64 {
+        self.outer_radius * pitch_radius_metres
+    }
+
+    /// whether a ray at `angle` passes through the pie
+    pub fn contains_angle(&self, angle: f64) -> bool {
+        (angle - self.min_angle).rem_euclid(TAU) <= self.sweep()
+    }
+}
+
+/// Where the point at `angle` and `radius` from the centre ends up on a screen whose y axis
+/// points down
+pub fn screen_point(centre_x: f64, centre_y: f64, radius: f64, angle: f64) -> (f64, f64) {
+    (centre_x - radius * angle.sin(), centre_y - radius * angle.cos())
+}
+
+/// The inverse of `screen_point`: the angle and radius of a point on screen relative to the centre
+pub fn polar_point(centre_x: f64, centre_y: f64, x: f64, y: f64) -> (f64, f64) {
+    let (dx, dy) = (centre_x - x, centre_y - y);
+    (dx.atan2(dy), dx.hypot(dy))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn timing(direction: f64, direction_area: f64, power: f64, power_area: f64) -> StrokeTiming {
+        StrokeTiming {
+            frame: 0,
+            vertical: 0,
+            direction: direction as i64,
+            direction_area: direction_area as i64,
+            power: power as i64,
+            power_area: power_area as i64,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn measures_angles_from_behind_the_batsman() {
+        let quarter = DIRECTION_UNITS_PER_TURN / 4.0;
+        let shape =
+            StrokeShape::of(&timing(DIRECTION_BEHIND_BATSMAN + quarter, quarter / 2.0, 0.0, 0.0));
+        assert_close(shape.centre_angle_degrees(), 90.0);
+        assert_close(shape.min_angle_degrees(), 45.0);
+        assert_close(shape.max_angle_degrees(), 135.0);
+        assert_close(shape.sweep(), PI / 2.0);
+    }
+
+    #[test]
+    fn wraps_pies_that_cross_behind_the_batsman() {
+        let shape = StrokeShape::of(&timing(
+            DIRECTION_BEHIND_BATSMAN,
+            DIRECTION_UNITS_PER_TURN / 8.0,
+            0.0,
+            0.0,
+        ));
+        assert_close(shape.min_angle_degrees(), -45.0);
+        assert_close(shape.sweep(), PI / 2.0);
+
+        let too_wide = StrokeShape::of(&timing(0.0, DIRECTION_UNITS_PER_TURN / 2.0, 0.0, 0.0));
+        assert!(too_wide.is_full_circle());
+    }
+
+    #[test]
+    fn scales_power_to_the_pitch_and_clamps_at_the_centre() {
+        let shape = StrokeShape::of(&timing(0.0, 0.0, 1_500_000.0, 2_000_000.0));
+        assert_close(shape.outer_radius, 3_500_000.0 / 4_500_000.0);
+        assert_close(shape.inner_radius, 0.0);
+        assert_close(shape.outer_radius_metres(72.0), 56.0);
+        assert_close(shape.inner_radius_metres(72.0), 0.0);
+    }
+
+    #[test]
+    fn places_angle_zero_at_the_top_of_the_screen() {
+        let (x, y) = screen_point(100.0, 100.0, 50.0, 0.0);
+        assert_close(x, 100.0);
+        assert_close(y, 50.0);
+        let (x, y) = screen_point(100.0, 100.0, 50.0, PI / 2.0);
+        assert_close(x, 50.0);
+        assert_close(y, 100.0);
+
+        let (angle, radius) = polar_point(100.0, 100.0, x, y);
+        assert_close(angle, PI / 2.0);
+        assert_close(radius, 50.0);
+    }
+
+    #[test]
+    fn contains_angles_between_min_and_max_across_zero() {
+        let shape = StrokeShape::of(&timing(
+            DIRECTION_BEHIND_BATSMAN,
+            DIRECTION_UNITS_PER_TURN / 8.0,
+            0.0,
+            0.0,
+        ));
+        assert!(shape.contains_angle(0.0));
+        assert!(shape.contains_angle(-PI / 8.0));
+        assert!(shape.contains_angle(TAU - PI / 8.0));
+        assert!(!shape.contains_angle(PI));
+    }
+}
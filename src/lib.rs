@@ -0,0 +1,20 @@
+//! Parsing and data handling for Cricket 2004 stroke configs, with no platform-specific
+//! dependencies. The Windows preview app is built on top of this.
+
+pub mod data;
+pub mod diff;
+pub mod document;
+pub mod edit;
+pub mod geometry;
+pub mod lint;
+pub mod list_file;
+pub mod offsets;
+pub mod raster;
+pub mod sections;
+pub mod sheet;
+pub mod strokes;
+pub mod svg;
+pub mod tolerant;
+
+#[cfg(test)]
+mod test_fixtures;
@@ -0,0 +1,240 @@
+//! Checks for stroke definitions that parse fine but probably don't do what the modder meant.
+
+use crate::geometry::DIRECTION_UNITS_PER_TURN;
+use crate::strokes::{Stroke, StrokeTiming};
+use std::fmt;
+
+/// the bowler type bits used by the shipped strokes, where 15 allows every kind of bowler. What
+/// each bit means isn't documented, so `unknown-bowler-types` is only a warning.
+pub const KNOWN_BOWLER_TYPES: i64 = 0b1111;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    check: fn(&Stroke) -> Vec<String>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "direction-area-wraps",
+        severity: Severity::Warning,
+        description: "DirectionArea is so wide that the pie covers the whole field",
+        check: direction_area_wraps,
+    },
+    Rule {
+        id: "frames-not-increasing",
+        severity: Severity::Error,
+        description: "the Frame of each keyframe must be later than the one before",
+        check: frames_not_increasing,
+    },
+    Rule {
+        id: "6hit-weaker-than-normal",
+        severity: Severity::Warning,
+        description: "a 6Hit keyframe has less Power than the Normal one",
+        check: six_hit_weaker_than_normal,
+    },
+    Rule {
+        id: "edge-prob-out-of-range",
+        severity: Severity::Error,
+        description: "EdgeProb and EdgeProb6 are percentages from 0 to 100",
+        check: edge_prob_out_of_range,
+    },
+    Rule {
+        id: "unknown-bowler-types",
+        severity: Severity::Warning,
+        description: "BowlerTypes has bits that match no bowler type, or no bits at all",
+        check: unknown_bowler_types,
+    },
+];
+
+/// A problem found by one of the `RULES`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// runs every rule over `stroke`
+pub fn lint(stroke: &Stroke) -> Vec<Finding> {
+    RULES
+        .iter()
+        .flat_map(|rule| {
+            (rule.check)(stroke).into_iter().map(move |message| Finding {
+                rule: rule.id,
+                severity: rule.severity,
+                message,
+            })
+        })
+        .collect()
+}
+
+/// e.g. "1 error, 2 warnings", or an empty string if there are no findings
+pub fn summary(findings: &[Finding]) -> String {
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let mut parts = Vec::new();
+    if count(Severity::Error) > 0 {
+        parts.push(plural(count(Severity::Error), "error"));
+    }
+    if count(Severity::Warning) > 0 {
+        parts.push(plural(count(Severity::Warning), "warning"));
+    }
+    parts.join(", ")
+}
+
+/// every keyframe with its name, Normal first
+fn keyframes(stroke: &Stroke) -> impl Iterator<Item = (String, &StrokeTiming)> {
+    modes(stroke).into_iter().flat_map(|(mode, timings)| {
+        timings.iter().enumerate().map(move |(i, t)| (format!("Mode {} KeyFrame {}", mode, i), t))
+    })
+}
+
+fn modes(stroke: &Stroke) -> Vec<(&'static str, &[StrokeTiming; 5])> {
+    vec![("Normal", &stroke.timings_normal), ("6Hit", &stroke.timings_6hit)]
+}
+
+fn direction_area_wraps(stroke: &Stroke) -> Vec<String> {
+    keyframes(stroke)
+        .filter(|(_, t)| 2.0 * t.direction_area_f64() >= DIRECTION_UNITS_PER_TURN)
+        .map(|(name, t)| {
+            format!("{}: DirectionArea {} covers the whole circle", name, t.direction_area)
+        })
+        .collect()
+}
+
+fn frames_not_increasing(stroke: &Stroke) -> Vec<String> {
+    let mut messages = Vec::new();
+    for (mode, timings) in modes(stroke) {
+        for (i, pair) in timings.windows(2).enumerate() {
+            if pair[1].frame <= pair[0].frame {
+                messages.push(format!(
+                    "Mode {}: KeyFrame {} is at Frame {}, which is not after KeyFrame {} at Frame {}",
+                    mode,
+                    i + 1,
+                    pair[1].frame,
+                    i,
+                    pair[0].frame
+                ));
+            }
+        }
+    }
+    messages
+}
+
+fn six_hit_weaker_than_normal(stroke: &Stroke) -> Vec<String> {
+    let pairs = stroke.timings_normal.iter().zip(stroke.timings_6hit.iter());
+    pairs
+        .enumerate()
+        .filter(|(_, (normal, six_hit))| six_hit.power < normal.power)
+        .map(|(i, (normal, six_hit))| {
+            format!(
+                "KeyFrame {}: 6Hit Power {} is less than Normal Power {}",
+                i, six_hit.power, normal.power
+            )
+        })
+        .collect()
+}
+
+fn edge_prob_out_of_range(stroke: &Stroke) -> Vec<String> {
+    [("EdgeProb", stroke.edge_prob), ("EdgeProb6", stroke.edge_prob6)]
+        .iter()
+        .filter(|(_, p)| !(0..=100).contains(p))
+        .map(|(key, p)| format!("{} {} is outside 0 to 100", key, p))
+        .collect()
+}
+
+fn unknown_bowler_types(stroke: &Stroke) -> Vec<String> {
+    let bits = stroke.bowler_types;
+    if bits == 0 {
+        vec!["BowlerTypes 0 allows no bowlers, so the stroke is never played".to_string()]
+    } else if bits & !KNOWN_BOWLER_TYPES != 0 {
+        vec![format!(
+            "BowlerTypes {} sets bits {:#b} that match no bowler type",
+            bits,
+            bits & !KNOWN_BOWLER_TYPES
+        )]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_fixtures::stroke;
+
+    fn rules(findings: &[Finding]) -> Vec<&str> {
+        findings.iter().map(|f| f.rule).collect()
+    }
+
+    #[test]
+    fn passes_a_sensible_stroke() {
+        assert_eq!(lint(&stroke()), []);
+        assert_eq!(summary(&[]), "");
+    }
+
+    #[test]
+    fn checks_the_power_and_direction_of_each_keyframe() {
+        let mut stroke = stroke();
+        stroke.timings_6hit[4].direction_area = 134535000;
+        stroke.timings_6hit[2].power = 100000;
+        let findings = lint(&stroke);
+        assert_eq!(rules(&findings), ["direction-area-wraps", "6hit-weaker-than-normal"]);
+        assert_eq!(
+            findings[0].to_string(),
+            "warning[direction-area-wraps]: Mode 6Hit KeyFrame 4: DirectionArea 134535000 \
+             covers the whole circle"
+        );
+        assert_eq!(
+            findings[1].message,
+            "KeyFrame 2: 6Hit Power 100000 is less than Normal Power 144631"
+        );
+        assert_eq!(summary(&findings), "2 warnings");
+    }
+
+    #[test]
+    fn checks_frames_edge_probabilities_and_bowler_types() {
+        let mut stroke = stroke();
+        stroke.timings_6hit[3].frame = 18;
+        stroke.edge_prob6 = 101;
+        stroke.bowler_types = 0b10011;
+        let findings = lint(&stroke);
+        let messages: Vec<_> = findings.iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "error[frames-not-increasing]: Mode 6Hit: KeyFrame 3 is at Frame 18, which is \
+                 not after KeyFrame 2 at Frame 18",
+                "error[edge-prob-out-of-range]: EdgeProb6 101 is outside 0 to 100",
+                "warning[unknown-bowler-types]: BowlerTypes 19 sets bits 0b10000 that match no \
+                 bowler type",
+            ]
+        );
+        stroke.bowler_types = 0;
+        assert_eq!(rules(&lint(&stroke)).last(), Some(&"unknown-bowler-types"));
+        assert_eq!(summary(&lint(&stroke)), "2 errors, 1 warning");
+    }
+}
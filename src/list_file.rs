@@ -0,0 +1,404 @@
+//! Parsing of List.txt, which names the entries in the .gob file as
+//! `description,offset,size` lines, with `//` comments.
+
+use anyhow::Context;
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+
+/// the offsets of the strokes in the .gob file
+/// https://www.planetcricket.org/forums/threads/config-editor-v3.8697/post-130389
+pub const STROKE_OFFSETS: RangeInclusive<i64> = 558891008..=559079424;
+/// the unit of the size column: strokes are `2`, two kilobyte slots. Nearly every entry in the
+/// bundled List.txt starts exactly its size in these units after the one before it, whatever
+/// its size, which only a few screens don't.
+pub const SIZE_UNIT: i64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Screen,
+    InmatchScreen,
+    Stroke,
+    BowlingTactic,
+    BowlingType,
+    Difficulty,
+    CpuPacing,
+    BallConditions,
+    Camera,
+    Other,
+}
+
+impl Category {
+    fn of(description: &str, offset: i64) -> Category {
+        let lower = description.to_ascii_lowercase();
+        if STROKE_OFFSETS.contains(&offset) {
+            Category::Stroke
+        } else if lower.starts_with("screen id") {
+            if lower.contains("(inmatch)") {
+                Category::InmatchScreen
+            } else {
+                Category::Screen
+            }
+        } else if lower.contains("(test)") || lower.contains("(odi)") {
+            Category::BowlingTactic
+        } else if lower.starts_with("bowl ") {
+            Category::BowlingType
+        } else if lower.starts_with("cpu pacing") {
+            Category::CpuPacing
+        } else if ["odi ", "test "].iter().any(|p| lower.starts_with(p))
+            && ["easy", "normal", "hard"].iter().any(|s| lower.ends_with(s))
+        {
+            Category::Difficulty
+        } else if lower.starts_with("ball conditions") {
+            Category::BallConditions
+        } else if lower.starts_with("camera") {
+            Category::Camera
+        } else {
+            Category::Other
+        }
+    }
+
+    /// whether the entry is one of the game's screens, which aren't in the AI export
+    pub fn is_screen(self) -> bool {
+        matches!(self, Category::Screen | Category::InmatchScreen)
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Screen => "screen",
+            Category::InmatchScreen => "inmatch screen",
+            Category::Stroke => "stroke",
+            Category::BowlingTactic => "bowling tactic",
+            Category::BowlingType => "bowling type",
+            Category::Difficulty => "difficulty",
+            Category::CpuPacing => "CPU pacing",
+            Category::BallConditions => "ball conditions",
+            Category::Camera => "camera",
+            Category::Other => "other",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListEntry {
+    pub description: String,
+    /// the address of the entry in the .gob file
+    pub offset: i64,
+    /// the size of the entry's slot in the .gob file, as given in the third column
+    pub size: i64,
+    pub category: Category,
+    /// 1-based line number in List.txt
+    pub line_number: usize,
+}
+
+impl ListEntry {
+    /// how many bytes the entry has in the .gob file, from the size column
+    pub fn slot_bytes(&self) -> i64 {
+        self.size * SIZE_UNIT
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListError {
+    pub line_number: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "List.txt line {}: {}", self.line_number, self.message)
+    }
+}
+
+/// Something wrong with the offsets in List.txt, found by `audit`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListWarning {
+    /// the lines of the entries involved
+    pub line_numbers: Vec<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ListWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The entries of a List.txt file in file order, along with the lines that could not be read
+#[derive(Debug, Default)]
+pub struct ListFile {
+    pub entries: Vec<ListEntry>,
+    pub errors: Vec<ListError>,
+}
+
+pub fn read_list_file(path: &str) -> anyhow::Result<ListFile> {
+    let bytes = fs::read(path).context("could not open list file")?;
+    Ok(parse_list_file(&String::from_utf8_lossy(&bytes)))
+}
+
+pub fn parse_list_file(text: &str) -> ListFile {
+    let mut list = ListFile::default();
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        if line.trim().is_empty() || line.starts_with("//") {
+            continue;
+        }
+        match parse_line(line) {
+            Ok((description, offset, size)) => list.entries.push(ListEntry {
+                category: Category::of(description, offset),
+                description: description.to_string(),
+                offset,
+                size,
+                line_number,
+            }),
+            Err(message) => list.errors.push(ListError { line_number, message }),
+        }
+    }
+    list
+}
+
+/// how warnings name an entry, e.g. `Hook 1 (List.txt line 309)`
+fn describe(entry: &ListEntry) -> String {
+    format!("{} (List.txt line {})", entry.description.trim(), entry.line_number)
+}
+
+/// `Off Spin (test) standard3` as `("Off Spin (test) standard", 3)`
+fn numbered(description: &str) -> Option<(&str, u32)> {
+    let description = description.trim();
+    let name = description.trim_end_matches(|c: char| c.is_ascii_digit());
+    Some((name, description[name.len()..].parse().ok()?))
+}
+
+/// Looks for entries that share an offset, slots that run into the next entry, and numbered
+/// entries like `defend4` and `defend5` whose offsets go backwards. Any of these means two names
+/// point at the same data, or a name points at the wrong data. Screens are left out of the slot
+/// check: they aren't in the AI export, and the bundled List.txt gives a handful of them sizes
+/// 2 to 4 units more than the gap to the next screen, which is known and harmless.
+pub fn audit(entries: &[ListEntry]) -> Vec<ListWarning> {
+    let mut warnings = Vec::new();
+    // a stable sort, so entries sharing an offset stay in file order
+    let mut by_offset: Vec<&ListEntry> = entries.iter().collect();
+    by_offset.sort_by_key(|e| e.offset);
+    let end = |e: &ListEntry| e.offset + e.slot_bytes();
+    // the entry whose slot reaches furthest so far
+    let mut furthest: Option<&ListEntry> = None;
+    for (i, &entry) in by_offset.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|p| by_offset[p]);
+        if let Some(previous) = previous.filter(|p| p.offset == entry.offset) {
+            warnings.push(ListWarning {
+                line_numbers: vec![previous.line_number, entry.line_number],
+                message: format!(
+                    "{} and {} both have offset {}",
+                    describe(previous),
+                    describe(entry),
+                    entry.offset
+                ),
+            });
+        } else if let Some(other) =
+            furthest.filter(|&f| !entry.category.is_screen() && end(f) > entry.offset)
+        {
+            warnings.push(ListWarning {
+                line_numbers: vec![other.line_number, entry.line_number],
+                message: format!(
+                    "the {} byte slot of {} at offset {} runs {} bytes into {} at offset {}",
+                    other.slot_bytes(),
+                    describe(other),
+                    other.offset,
+                    end(other) - entry.offset,
+                    describe(entry),
+                    entry.offset
+                ),
+            });
+        }
+        match furthest {
+            _ if entry.category.is_screen() => {}
+            Some(f) if end(f) >= end(entry) => {}
+            _ => furthest = Some(entry),
+        }
+    }
+    for pair in entries.windows(2) {
+        let (first, second) = (&pair[0], &pair[1]);
+        match (numbered(&first.description), numbered(&second.description)) {
+            (Some((name, n)), Some((next_name, next_n)))
+                if name == next_name && next_n == n + 1 && second.offset < first.offset =>
+            {
+                warnings.push(ListWarning {
+                    line_numbers: vec![first.line_number, second.line_number],
+                    message: format!(
+                        "{} has offset {}, which is before {} at offset {}",
+                        describe(second),
+                        second.offset,
+                        describe(first),
+                        first.offset
+                    ),
+                })
+            }
+            _ => {}
+        }
+    }
+    warnings
+}
+
+/// splits a line into description, offset and size. Only the last two fields are numbers, so
+/// descriptions may contain commas.
+fn parse_line(line: &str) -> Result<(&str, i64, i64), String> {
+    let mut parts = line.rsplitn(3, ',');
+    let (size, offset, description) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(size), Some(offset), Some(description)) => (size, offset, description),
+        _ => {
+            return Err(format!(
+                "expected `description,offset,size` but found {} fields",
+                line.split(',').count()
+            ))
+        }
+    };
+    let number = |name: &str, value: &str| {
+        value.trim().parse::<i64>().map_err(|e| format!("invalid {} {:?}: {}", name, value, e))
+    };
+    Ok((description, number("offset", offset)?, number("size", size)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_entries_and_categories() {
+        let list = parse_list_file(
+            "//Colin's List.txt file\n\
+             Screen ID 0 = main menu,65146881,8\n\
+             Screen ID 0 (inmatch) = pause menu,498188289,16\n\
+             ODI Easy,25665537,2\n\
+             CPU Pacing ODI - 10 overs,25673729,4\n\
+             Batpad (med/fast),558891009,2\n\
+             Slow (ODI) defend1,26687489,2\n\
+             Bowl arm ball,25538561,2\n",
+        );
+        assert_eq!(list.errors, []);
+        assert_eq!(
+            list.entries[4],
+            ListEntry {
+                description: "Batpad (med/fast)".to_string(),
+                offset: 558891009,
+                size: 2,
+                category: Category::Stroke,
+                line_number: 6,
+            }
+        );
+        let categories: Vec<_> = list.entries.iter().map(|e| e.category).collect();
+        assert_eq!(
+            categories,
+            [
+                Category::Screen,
+                Category::InmatchScreen,
+                Category::Difficulty,
+                Category::CpuPacing,
+                Category::Stroke,
+                Category::BowlingTactic,
+                Category::BowlingType,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_bad_lines_with_line_numbers() {
+        let list = parse_list_file("Hook 1,559036417,2\nHook 2,55903x465,2\nHook 3\n");
+        assert_eq!(list.entries.len(), 1);
+        let errors: Vec<_> = list.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            [
+                "List.txt line 2: invalid offset \"55903x465\": invalid digit found in string",
+                "List.txt line 3: expected `description,offset,size` but found 1 fields",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_commas_in_descriptions() {
+        let list = parse_list_file(
+            "Straight drive 2(step out to offstump/swipe to longoff/like cover drive),558995457,2\n\
+             Straight drive 2(step out to offstump, swipe to longoff, like cover drive),558995457,2\n\
+             Cover drive 6 hit( high follow,weight on back foot,standing tall),558989313,2\n",
+        );
+        assert_eq!(list.errors, []);
+        let entries: Vec<_> =
+            list.entries.iter().map(|e| (e.description.as_str(), e.offset, e.size)).collect();
+        assert_eq!(
+            entries,
+            [
+                (
+                    "Straight drive 2(step out to offstump/swipe to longoff/like cover drive)",
+                    558995457,
+                    2
+                ),
+                (
+                    "Straight drive 2(step out to offstump, swipe to longoff, like cover drive)",
+                    558995457,
+                    2
+                ),
+                ("Cover drive 6 hit( high follow,weight on back foot,standing tall)", 558989313, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_a_comma_description_without_numbers_at_the_end() {
+        let list = parse_list_file("Hook 1, the short one,559036417\n");
+        assert_eq!(list.entries, []);
+        assert_eq!(
+            list.errors[0].to_string(),
+            "List.txt line 1: invalid offset \" the short one\": invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn audits_shared_offsets_overlapping_slots_and_numbering() {
+        let list = parse_list_file(
+            "Slow (ODI) normal1,26697729,2\n\
+             Slow (ODI) normal2,26697729,2\n\
+             Off Spin (test) standard2,26669057,2\n\
+             Off Spin (test) standard3,26640385,2\n\
+             Camera,25593857,8\n\
+             Ball Conditions,25600001,2\n\
+             Screen ID 2 = change line up,62947329,178\n\
+             Screen ID 158 = main menu (not used),63125505,8\n",
+        );
+        let warnings = audit(&list.entries);
+        let messages: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "the 8192 byte slot of Camera (List.txt line 5) at offset 25593857 runs 2048 \
+                 bytes into Ball Conditions (List.txt line 6) at offset 25600001",
+                "Slow (ODI) normal1 (List.txt line 1) and Slow (ODI) normal2 (List.txt line 2) \
+                 both have offset 26697729",
+                "Off Spin (test) standard3 (List.txt line 4) has offset 26640385, which is before \
+                 Off Spin (test) standard2 (List.txt line 3) at offset 26669057",
+            ]
+        );
+        assert_eq!(warnings[0].line_numbers, [5, 6]);
+    }
+
+    #[test]
+    fn parses_the_bundled_list_file() {
+        let list = read_list_file(concat!(env!("CARGO_MANIFEST_DIR"), "/List.txt")).unwrap();
+        assert_eq!(list.errors, []);
+        assert_eq!(list.entries.len(), 456);
+        assert_eq!(list.entries.iter().filter(|e| e.category == Category::Stroke).count(), 85);
+        let warnings: Vec<_> = audit(&list.entries).iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            [
+                "Slow (ODI) normal1 (List.txt line 439) and Slow (ODI) normal2 (List.txt line \
+                 440) both have offset 26697729",
+                "Off Spin (test) standard3 (List.txt line 401) has offset 26640385, which is \
+                 before Off Spin (test) standard2 (List.txt line 400) at offset 26669057",
+                "Fast (ODI) defend5 (List.txt line 408) has offset 26480641, which is before \
+                 Fast (ODI) defend4 (List.txt line 407) at offset 26488833",
+            ]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Mapping between List.txt offsets, which are addresses in the .gob file, and positions in the
+//! exported AI.cfg file.
+
+use crate::document::{Block, CfgDocument, Span};
+use crate::list_file::{ListEntry, ListWarning};
+use std::fmt;
+
+/// offset found experimentally
+const BYTES_OF_NON_STROKES: i64 = 0x7c60;
+/// https://www.planetcricket.org/forums/threads/config-editor-v3.8697/post-130389
+/// offset of first stroke
+const FIRST_STROKE_OFFSET: i64 = 558891009;
+/// what to add to a List.txt offset to get a position in an unmodified cfg file
+pub const BUILT_IN_DELTA: i64 = BYTES_OF_NON_STROKES - FIRST_STROKE_OFFSET;
+
+/// How List.txt offsets map onto a particular cfg file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    /// what to add to a List.txt offset to get a position in the cfg file
+    pub delta: i64,
+    /// how many entries land exactly on the start of a stroke block with this delta
+    pub aligned: usize,
+    /// how many more entries were found by following blocks moved by edits before them
+    pub moved: usize,
+    pub entries: usize,
+}
+
+impl Calibration {
+    /// the position in the cfg file of the List.txt entry at `offset`
+    pub fn cfg_position_of(&self, offset: i64) -> i64 {
+        offset + self.delta
+    }
+
+    pub fn is_built_in(&self) -> bool {
+        self.delta == BUILT_IN_DELTA
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Calibration {
+        Calibration { delta: BUILT_IN_DELTA, aligned: 0, moved: 0, entries: 0 }
+    }
+}
+
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset delta {}", self.delta)?;
+        if !self.is_built_in() {
+            write!(f, " (built in: {})", BUILT_IN_DELTA)?;
+        }
+        write!(f, ", {} of {} stroke entries start a block", self.aligned, self.entries)?;
+        if self.moved > 0 {
+            write!(f, " and {} more were moved by edits before them", self.moved)?;
+        }
+        Ok(())
+    }
+}
+
+/// Puts the entry with the lowest offset on the first block. Hand edits only move the blocks
+/// after them, so the first block is the one place they can't have moved, whereas a vote over
+/// every block would follow whichever delta most of the edited file happens to agree on. With
+/// no blocks or no entries the built-in delta is used.
+pub fn calibrate(block_starts: &[usize], entries: &[ListEntry]) -> Calibration {
+    let first_block = block_starts.iter().min();
+    let first_entry = entries.iter().map(|e| e.offset).min();
+    let delta = match (first_block, first_entry) {
+        (Some(&block), Some(entry)) => block as i64 - entry,
+        _ => BUILT_IN_DELTA,
+    };
+    let aligned = entries
+        .iter()
+        .filter(|e| {
+            let position = e.offset + delta;
+            position >= 0 && block_starts.contains(&(position as usize))
+        })
+        .count();
+    Calibration { delta, aligned, moved: 0, entries: entries.len() }
+}
+
+/// A `StrokeAttributes` block found in the cfg file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocatedStroke {
+    /// the block, from its header up to the NUL that ends it
+    pub span: Span,
+    /// 1-based line of the header in the cfg file
+    pub line_number: usize,
+}
+
+impl LocatedStroke {
+    /// the space the stroke needs in its slot in the .gob file, counting the NUL that ends it
+    pub fn bytes_needed(&self) -> i64 {
+        self.span.len() as i64 + 1
+    }
+}
+
+/// Describes how far `stroke` overruns the slot List.txt gives `entry`, if it does. Config
+/// Editor can't import a stroke that doesn't fit.
+pub fn over_budget(entry: &ListEntry, stroke: &LocatedStroke) -> Option<String> {
+    let (needed, slot) = (stroke.bytes_needed(), entry.slot_bytes());
+    if needed <= slot {
+        return None;
+    }
+    Some(format!(
+        "{} (cfg line {}) needs {} bytes, {} more than the {} byte slot List.txt line {} gives it",
+        entry.description.trim(),
+        stroke.line_number,
+        needed,
+        needed - slot,
+        slot,
+        entry.line_number,
+    ))
+}
+
+#[derive(Debug, Default)]
+pub struct StrokeLocations {
+    /// the stroke block for each entry, in the same order as the entries
+    pub strokes: Vec<Option<LocatedStroke>>,
+    /// places where List.txt and the blocks in the cfg file disagree
+    pub warnings: Vec<String>,
+    /// entries whose offset lands inside a block rather than at its start, which belong with
+    /// the rest of the `list_file::audit` warnings
+    pub mid_block: Vec<ListWarning>,
+    pub calibration: Calibration,
+}
+
+/// Matches stroke entries from List.txt, sorted by offset, to the `StrokeAttributes` blocks in the
+/// cfg file. When there are as many blocks as entries they are matched in order, which doesn't
+/// depend on the length of whatever comes before the strokes; otherwise each entry gets the block
+/// nearest its offset, after calibrating offsets against the blocks. Entries whose offset points
+/// somewhere else are reported either way.
+pub fn locate_strokes(document: &CfgDocument, entries: &[ListEntry]) -> StrokeLocations {
+    let blocks: Vec<LocatedStroke> = document
+        .stroke_blocks()
+        .map(|b| LocatedStroke { span: b.span, line_number: document.location(b.span.start).line })
+        .collect();
+    let starts: Vec<usize> = blocks.iter().map(|b| b.span.start).collect();
+    let mut calibration = calibrate(&starts, entries);
+
+    // hand edits change the length of a block and move everything after it, so follow the
+    // drift from one entry to the next rather than expecting every block exactly at its offset
+    let mut drift = 0;
+    let mut at_offsets = Vec::with_capacity(entries.len());
+    let mut mid_block = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let expected = calibration.cfg_position_of(entry.offset) + drift;
+        let inside = |b: &&Block| (b.span.start as i64) < expected && expected < b.span.end as i64;
+        if let Some(block) = document.blocks().iter().find(inside) {
+            mid_block.push(ListWarning {
+                line_numbers: vec![entry.line_number],
+                message: format!(
+                    "{} (List.txt line {}): offset {} lands {} bytes into the block on cfg line {}",
+                    entry.description.trim(),
+                    entry.line_number,
+                    entry.offset,
+                    expected - block.span.start as i64,
+                    document.location(block.span.start).line,
+                ),
+            });
+        }
+        let reach = |other: Option<&ListEntry>| {
+            other.map_or(entry.slot_bytes() / 2, |o| (o.offset - entry.offset).abs() / 2)
+        };
+        let range = expected - reach(i.checked_sub(1).map(|p| &entries[p]))
+            ..=expected + reach(entries.get(i + 1));
+        let at_offset = blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| range.contains(&(b.span.start as i64)))
+            .min_by_key(|(_, b)| (b.span.start as i64 - expected).abs())
+            .map(|(i, _)| i);
+        if let Some(b) = at_offset {
+            drift += blocks[b].span.start as i64 - expected;
+            if drift != 0 {
+                calibration.moved += 1;
+            }
+        }
+        at_offsets.push(at_offset);
+    }
+    let match_in_order = blocks.len() == entries.len();
+
+    let mut locations = StrokeLocations { calibration, mid_block, ..StrokeLocations::default() };
+    for (i, (entry, &at_offset)) in entries.iter().zip(&at_offsets).enumerate() {
+        let chosen = if match_in_order { Some(i) } else { at_offset };
+        match (chosen, at_offset) {
+            (Some(chosen), Some(at_offset)) if chosen != at_offset => {
+                locations.warnings.push(format!(
+                    "{} (List.txt line {}) is stroke {} in order, on cfg line {}, but its offset \
+                     points at the stroke on cfg line {}",
+                    entry.description.trim(),
+                    entry.line_number,
+                    i + 1,
+                    blocks[chosen].line_number,
+                    blocks[at_offset].line_number,
+                ));
+            }
+            (_, None) => locations.warnings.push(format!(
+                "{} (List.txt line {}): no StrokeAttributes block starts at its offset {}",
+                entry.description.trim(),
+                entry.line_number,
+                entry.offset,
+            )),
+            _ => {}
+        }
+        locations.strokes.push(chosen.map(|b| blocks[b]));
+    }
+    locations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::list_file::parse_list_file;
+
+    fn cfg(preamble: usize, strokes: usize) -> Vec<u8> {
+        let mut cfg = b"Camera\r\n".to_vec();
+        cfg.resize(preamble, 0);
+        for _ in 0..strokes {
+            let start = cfg.len();
+            cfg.extend_from_slice(b"SStrokeAttributes\r\nType Attacking");
+            cfg.resize(start + 2048, 0);
+        }
+        cfg
+    }
+
+    const LIST: &str = "Batpad (med/fast),558891009,2\nBatpad (Spin),558893057,2\n";
+
+    #[test]
+    fn calibrates_an_unmodified_file_to_the_built_in_delta() {
+        let cfg = cfg(0x7c60, 2);
+        let document = CfgDocument::parse(&cfg);
+        let calibration = locate_strokes(&document, &parse_list_file(LIST).entries).calibration;
+        assert!(calibration.is_built_in());
+        assert_eq!(calibration.cfg_position_of(558891009), 0x7c60);
+        assert_eq!(calibration.cfg_position_of(558893057), 0x7c60 + 2048);
+        assert_eq!(
+            calibration.to_string(),
+            "offset delta -558859169, 2 of 2 stroke entries start a block"
+        );
+    }
+
+    #[test]
+    fn finds_strokes_at_their_offsets() {
+        let cfg = cfg(0x7c60, 2);
+        let document = CfgDocument::parse(&cfg);
+        let locations = locate_strokes(&document, &parse_list_file(LIST).entries);
+        assert_eq!(locations.warnings, Vec::<String>::new());
+        let starts: Vec<_> = locations.strokes.iter().map(|s| s.unwrap().span.start).collect();
+        assert_eq!(starts, [0x7c60, 0x7c60 + 2048]);
+        assert_eq!(locations.strokes[0].unwrap().line_number, 2);
+    }
+
+    #[test]
+    fn calibrates_a_file_with_a_longer_preamble() {
+        let cfg = cfg(0x7c60 + 2148, 2);
+        let document = CfgDocument::parse(&cfg);
+        let locations = locate_strokes(&document, &parse_list_file(LIST).entries);
+        assert_eq!(locations.warnings, Vec::<String>::new());
+        let starts: Vec<_> = locations.strokes.iter().map(|s| s.unwrap().span.start).collect();
+        assert_eq!(starts, [0x7c60 + 2148, 0x7c60 + 4196]);
+        assert_eq!(
+            locations.calibration.to_string(),
+            "offset delta -558857021 (built in: -558859169), 2 of 2 stroke entries start a block"
+        );
+    }
+
+    #[test]
+    fn matches_in_order_and_warns_when_offsets_point_at_another_stroke() {
+        let cfg = cfg(0x7c60, 3);
+        let document = CfgDocument::parse(&cfg);
+        let list =
+            "Batpad (med/fast),558891009,2\nEdge fast 1,558895105,2\nEdge fast 2,558897153,2\n";
+        let locations = locate_strokes(&document, &parse_list_file(list).entries);
+        let starts: Vec<_> = locations.strokes.iter().map(|s| s.unwrap().span.start).collect();
+        assert_eq!(starts, [0x7c60, 0x7c60 + 2048, 0x7c60 + 4096]);
+        assert_eq!(
+            locations.warnings,
+            [
+                "Edge fast 1 (List.txt line 2) is stroke 2 in order, on cfg line 3, but its \
+                 offset points at the stroke on cfg line 4",
+                "Edge fast 2 (List.txt line 3): no StrokeAttributes block starts at its offset \
+                 558897153",
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_blocks_moved_by_edits_before_them() {
+        let mut cfg = cfg(0x7c60, 3);
+        let edit = 0x7c60 + "SStrokeAttributes\r\nType Attacking".len();
+        cfg.splice(edit..edit, b" // was defensive".iter().copied());
+        let document = CfgDocument::parse(&cfg);
+        let list = "Batpad (med/fast),558891009,2\nEdge fast 1,558895105,2\n";
+        let locations = locate_strokes(&document, &parse_list_file(list).entries);
+        assert_eq!(locations.warnings, Vec::<String>::new());
+        let starts: Vec<_> = locations.strokes.iter().map(|s| s.unwrap().span.start).collect();
+        assert_eq!(starts, [0x7c60, 0x7c60 + 4096 + 17]);
+    }
+
+    #[test]
+    fn keeps_the_built_in_delta_when_an_early_block_is_shortened() {
+        let mut cfg = cfg(0x7c60, 3);
+        let edit = 0x7c60 + "SStrokeAttributes\r\nType Attack".len();
+        cfg.drain(edit..edit + 3);
+        let document = CfgDocument::parse(&cfg);
+        let list = format!("{}Edge fast 1,558895105,2\n", LIST);
+        let locations = locate_strokes(&document, &parse_list_file(&list).entries);
+        assert_eq!(locations.warnings, Vec::<String>::new());
+        let starts: Vec<_> = locations.strokes.iter().map(|s| s.unwrap().span.start).collect();
+        assert_eq!(starts, [0x7c60, 0x7c60 + 2045, 0x7c60 + 4093]);
+        assert_eq!(
+            locations.calibration.to_string(),
+            "offset delta -558859169, 1 of 3 stroke entries start a block and 2 more were moved \
+             by edits before them"
+        );
+    }
+
+    #[test]
+    fn checks_strokes_against_the_size_of_their_slot() {
+        let mut cfg = cfg(0x7c60, 2);
+        let document = CfgDocument::parse(&cfg);
+        let entries = parse_list_file(LIST).entries;
+        let locations = locate_strokes(&document, &entries);
+        let first = locations.strokes[0].unwrap();
+        assert_eq!(first.bytes_needed(), "SStrokeAttributes\r\nType Attacking".len() as i64 + 1);
+        assert_eq!(over_budget(&entries[0], &first), None);
+
+        let end = first.span.end;
+        // grow the block to fill the slot, leaving no room for its NUL
+        let growth = 2048 - first.span.len();
+        cfg.splice(end..end, vec![b'x'; growth]);
+        let document = CfgDocument::parse(&cfg);
+        let stroke = locate_strokes(&document, &entries).strokes[0].unwrap();
+        assert_eq!(
+            over_budget(&entries[0], &stroke).unwrap(),
+            "Batpad (med/fast) (cfg line 2) needs 2049 bytes, 1 more than the 2048 byte slot \
+             List.txt line 1 gives it"
+        );
+    }
+
+    #[test]
+    fn reports_entries_without_a_block_when_counts_differ() {
+        let cfg = cfg(0x7c60, 2);
+        let document = CfgDocument::parse(&cfg);
+        let list = format!("{}Edge fast 1,558895105,2\n", LIST);
+        let locations = locate_strokes(&document, &parse_list_file(&list).entries);
+        assert!(locations.strokes[1].is_some());
+        assert_eq!(locations.strokes[2], None);
+        assert_eq!(locations.warnings.len(), 1);
+    }
+
+    #[test]
+    fn reports_offsets_that_land_inside_a_block() {
+        let cfg = cfg(0x7c60, 2);
+        let document = CfgDocument::parse(&cfg);
+        let list = LIST.replace("558893057", "558893077");
+        let locations = locate_strokes(&document, &parse_list_file(&list).entries);
+        assert!(locations.strokes[1].is_some());
+        assert_eq!(locations.warnings, Vec::<String>::new());
+        assert_eq!(
+            locations.mid_block,
+            [ListWarning {
+                line_numbers: vec![2],
+                message: "Batpad (Spin) (List.txt line 2): offset 558893077 lands 20 bytes into \
+                          the block on cfg line 3"
+                    .to_string(),
+            }]
+        );
+    }
+}
@@ -1,9 +1,14 @@
-use crate::strokes::{Stroke, StrokeTiming};
 use std::cmp::min;
-use std::f64::consts::TAU;
-use winapi::shared::windef::{HBRUSH, HDC, RECT};
+use std::ptr::null_mut;
+use stroke_preview::geometry::{
+    screen_point, Rgb, StrokeShape, DARK_GREEN, GREEN, OVERLAY, PITCH_PADDING, STROKE_MAX,
+    STROKE_MIN,
+};
+use stroke_preview::strokes::{Stroke, StrokeTiming};
+use winapi::shared::windef::{HBRUSH, HDC, HPEN, RECT};
 use winapi::um::wingdi::{
-    CreateSolidBrush, Ellipse, GetStockObject, Pie, SelectObject, NULL_PEN, RGB, WHITE_PEN,
+    Arc, CreatePen, CreateSolidBrush, Ellipse, GetStockObject, LineTo, MoveToEx, Pie, SelectObject,
+    NULL_BRUSH, NULL_PEN, PS_SOLID, RGB, WHITE_PEN,
 };
 use winapi::um::winuser::{FillRect, GetSysColorBrush, COLOR_MENU};
 
@@ -15,26 +20,33 @@ pub struct PitchPainter {
 
     stroke_min: HBRUSH,
     stroke_max: HBRUSH,
+
+    overlay: HPEN,
 }
 
 impl PitchPainter {
     pub fn new() -> PitchPainter {
+        let rgb = |[red, green, blue]: Rgb| RGB(red, green, blue);
         unsafe {
             PitchPainter {
                 // FIXME: native-windows-gui sets the wrong background color and we can't change it
                 background: GetSysColorBrush(COLOR_MENU),
-                green: CreateSolidBrush(RGB(0, 190, 0)),
-                dark_green: CreateSolidBrush(RGB(0, 150, 0)),
-                stroke_min: CreateSolidBrush(RGB(250, 100, 50)),
-                stroke_max: CreateSolidBrush(RGB(250, 250, 30)),
+                green: CreateSolidBrush(rgb(GREEN)),
+                dark_green: CreateSolidBrush(rgb(DARK_GREEN)),
+                stroke_min: CreateSolidBrush(rgb(STROKE_MIN)),
+                stroke_max: CreateSolidBrush(rgb(STROKE_MAX)),
+                overlay: CreatePen(PS_SOLID as _, 2, rgb(OVERLAY)),
             }
         }
     }
 
+    /// Paints `stroke` with the selected timing of `overlay` outlined on top, to show how an
+    /// edit moved it
     pub fn paint(
         &self,
         paint: &nwg::PaintData,
         stroke: Option<&Stroke>,
+        overlay: Option<&Stroke>,
         selected_timing: usize,
         selected_6hit: bool,
     ) {
@@ -48,7 +60,7 @@ impl PitchPainter {
 
             // pitch
 
-            let padding = 5;
+            let padding = PITCH_PADDING as i32;
             SelectObject(hdc, self.green as _);
             SelectObject(hdc, GetStockObject(WHITE_PEN as _));
             // make sure it's round
@@ -79,6 +91,12 @@ impl PitchPainter {
                 }
                 self.paint_stroke_segment(hdc, &timings[selected_timing], bounds, true);
             }
+
+            if let Some(overlay) = overlay {
+                let timings =
+                    if selected_6hit { &overlay.timings_6hit } else { &overlay.timings_normal };
+                self.paint_outline(hdc, &timings[selected_timing], bounds);
+            }
         }
 
         paint.end_paint(&ps);
@@ -91,46 +109,99 @@ impl PitchPainter {
         bounds: RECT,
         highlighted: bool,
     ) {
-        let centre_x = (bounds.left + bounds.right) as f64 / 2.0;
-        let centre_y = (bounds.top + bounds.bottom) as f64 / 2.0;
+        let pie = PieLayout::of(stroke, bounds);
 
-        let pitch_radius = (bounds.right - bounds.left) as f64 / 2.0; // it's a circle
-
-        // angle is in radians anticlockwise 0 being directly behind the batsman
-        let angle = (stroke.direction as f64 - 60_000.0) / 269_070_000.0 * TAU;
+        for &(radius, brush) in
+            [(pie.shape.outer_radius, self.stroke_max), (pie.shape.inner_radius, self.stroke_min)]
+                .iter()
+        {
+            let (left, top, right, bottom) = pie.circle(radius);
 
-        let min_angle = (angle - stroke.direction_area / 269_070_000.0 * TAU) % TAU;
-        let max_angle = (angle + stroke.direction_area / 269_070_000.0 * TAU) % TAU;
+            unsafe {
+                SelectObject(hdc, if highlighted { brush } else { self.dark_green } as _);
 
-        let min_radial_intercept_x = centre_x - 100.0 * min_angle.sin();
-        let min_radial_intercept_y = centre_y - 100.0 * min_angle.cos();
+                Pie(hdc, left, top, right, bottom, pie.start.0, pie.start.1, pie.end.0, pie.end.1);
+            }
+        }
+    }
 
-        let max_radial_intercept_x = centre_x - 100.0 * max_angle.sin();
-        let max_radial_intercept_y = centre_y - 100.0 * max_angle.cos();
+    /// outlines the window between the inner and outer radius of `stroke` without filling it
+    fn paint_outline(&self, hdc: HDC, stroke: &StrokeTiming, bounds: RECT) {
+        let pie = PieLayout::of(stroke, bounds);
+        let shape = pie.shape;
 
-        for &(radius_unscaled, brush) in [
-            (stroke.power + stroke.power_area, self.stroke_max),
-            (stroke.power - stroke.power_area, self.stroke_min),
-        ]
-        .iter()
-        {
-            let shot_radius = pitch_radius * radius_unscaled / 4_500_000.0;
+        unsafe {
+            SelectObject(hdc, self.overlay as _);
+            SelectObject(hdc, GetStockObject(NULL_BRUSH as _));
 
-            unsafe {
-                SelectObject(hdc, if highlighted { brush } else { self.dark_green } as _);
+            for &radius in [shape.outer_radius, shape.inner_radius].iter() {
+                if pie.pitch_radius * radius < 1.0 {
+                    continue;
+                }
+                let (left, top, right, bottom) = pie.circle(radius);
+                // like Pie, Arc draws a full circle when the start and end are the same
+                Arc(hdc, left, top, right, bottom, pie.start.0, pie.start.1, pie.end.0, pie.end.1);
+            }
 
-                Pie(
-                    hdc,
-                    (centre_x - shot_radius) as i32,
-                    (centre_y - shot_radius) as i32,
-                    (centre_x + shot_radius) as i32,
-                    (centre_y + shot_radius) as i32,
-                    min_radial_intercept_x as i32,
-                    min_radial_intercept_y as i32,
-                    max_radial_intercept_x as i32,
-                    max_radial_intercept_y as i32,
-                );
+            if !shape.is_full_circle() {
+                for &angle in [shape.min_angle, shape.max_angle].iter() {
+                    let (inner_x, inner_y) = pie.point(shape.inner_radius, angle);
+                    let (outer_x, outer_y) = pie.point(shape.outer_radius, angle);
+                    MoveToEx(hdc, inner_x as i32, inner_y as i32, null_mut());
+                    LineTo(hdc, outer_x as i32, outer_y as i32);
+                }
             }
         }
     }
 }
+
+/// Where the pie of one keyframe goes in the pitch circle drawn in `bounds`
+struct PieLayout {
+    centre_x: f64,
+    centre_y: f64,
+    pitch_radius: f64,
+    shape: StrokeShape,
+    /// points on the rays at `min_angle` and `max_angle`, as `Pie` and `Arc` take them
+    start: (i32, i32),
+    end: (i32, i32),
+}
+
+impl PieLayout {
+    fn of(stroke: &StrokeTiming, bounds: RECT) -> PieLayout {
+        let centre_x = (bounds.left + bounds.right) as f64 / 2.0;
+        let centre_y = (bounds.top + bounds.bottom) as f64 / 2.0;
+
+        let pitch_radius = (bounds.right - bounds.left) as f64 / 2.0; // it's a circle
+
+        let shape = StrokeShape::of(stroke);
+
+        let radial_intercept = |angle: f64| {
+            let (x, y) = screen_point(centre_x, centre_y, 100.0, angle);
+            (x as i32, y as i32)
+        };
+        PieLayout {
+            centre_x,
+            centre_y,
+            pitch_radius,
+            shape,
+            start: radial_intercept(shape.min_angle),
+            end: radial_intercept(shape.max_angle),
+        }
+    }
+
+    /// the bounding box of the circle at `radius`, a fraction of the pitch radius
+    fn circle(&self, radius: f64) -> (i32, i32, i32, i32) {
+        let shot_radius = self.pitch_radius * radius;
+        (
+            (self.centre_x - shot_radius) as i32,
+            (self.centre_y - shot_radius) as i32,
+            (self.centre_x + shot_radius) as i32,
+            (self.centre_y + shot_radius) as i32,
+        )
+    }
+
+    /// the point on screen at `radius`, a fraction of the pitch radius, and `angle`
+    fn point(&self, radius: f64, angle: f64) -> (f64, f64) {
+        screen_point(self.centre_x, self.centre_y, self.pitch_radius * radius, angle)
+    }
+}
@@ -0,0 +1,185 @@
+//! Draws the pitch diagram into an in-memory image and encodes it as PNG, without needing a
+//! window or a GPU.
+
+use crate::geometry::{
+    polar_point, Rgb, StrokeShape, DARK_GREEN, GREEN, PITCH_PADDING, SHEET_COLUMNS, STROKE_MAX,
+    STROKE_MIN,
+};
+use crate::strokes::Stroke;
+use std::io::Write;
+
+/// the default Windows menu colour, which is what the preview window ends up with
+const BACKGROUND: Rgb = [240, 240, 240];
+const WHITE: Rgb = [255, 255, 255];
+
+/// samples per pixel along each axis, to smooth the edges of the pies
+const SUPERSAMPLING: u32 = 3;
+
+/// the most pixels in one image, which keeps an image to about 50 MB and its drawing to seconds
+pub const MAX_PIXELS: u64 = 4096 * 4096;
+
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// rows of RGB pixels, top to bottom
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32) -> Image {
+        Image { width, height, pixels: BACKGROUND.repeat(width as usize * height as usize) }
+    }
+
+    fn set(&mut self, x: u32, y: u32, colour: Rgb) {
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&colour);
+    }
+
+    pub fn write_png<W: Write>(&self, w: W) -> anyhow::Result<()> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+/// Something painted onto the diagram, covering whatever was painted before it
+enum Layer {
+    Disc { radius: f64, colour: Rgb },
+    Pie { shape: StrokeShape, radius: f64, colour: Rgb },
+}
+
+impl Layer {
+    fn colour_at(&self, angle: f64, distance: f64) -> Option<Rgb> {
+        match *self {
+            Layer::Disc { radius, colour } if distance <= radius => Some(colour),
+            Layer::Pie { ref shape, radius, colour }
+                if distance <= radius && shape.contains_angle(angle) =>
+            {
+                Some(colour)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Renders the pitch and the five timings of one mode of `stroke`, with `selected_timing`
+/// highlighted, as an image `size` pixels square.
+pub fn render_stroke(
+    stroke: Option<&Stroke>,
+    selected_timing: usize,
+    selected_6hit: bool,
+    size: u32,
+) -> Image {
+    let mut image = Image::new(size, size);
+    draw_stroke(&mut image, 0, 0, size, stroke, selected_timing, selected_6hit);
+    image
+}
+
+/// Renders every keyframe of `stroke` highlighted in turn, in a grid of `size` pixel cells with
+/// the Normal keyframes on the top row and the 6Hit keyframes below, very early to very late.
+pub fn render_contact_sheet(stroke: &Stroke, size: u32) -> Image {
+    let (width, height) = contact_sheet_dimensions(size);
+    let mut image = Image::new(width, height);
+    for (row, &selected_6hit) in [false, true].iter().enumerate() {
+        for column in 0..SHEET_COLUMNS {
+            let (left, top) = (column as u32 * size, row as u32 * size);
+            draw_stroke(&mut image, left, top, size, Some(stroke), column, selected_6hit);
+        }
+    }
+    image
+}
+
+/// the width and height of the image `render_contact_sheet` draws with `size` pixel cells
+pub fn contact_sheet_dimensions(size: u32) -> (u32, u32) {
+    (size * SHEET_COLUMNS as u32, size * 2)
+}
+
+fn draw_stroke(
+    image: &mut Image,
+    left: u32,
+    top: u32,
+    size: u32,
+    stroke: Option<&Stroke>,
+    selected_timing: usize,
+    selected_6hit: bool,
+) {
+    let pitch_radius = size as f64 / 2.0 - PITCH_PADDING;
+    let mut layers = vec![
+        Layer::Disc { radius: pitch_radius, colour: WHITE },
+        Layer::Disc { radius: pitch_radius - 1.0, colour: GREEN },
+    ];
+    if let Some(stroke) = stroke {
+        let timings = if selected_6hit { &stroke.timings_6hit } else { &stroke.timings_normal };
+        let unselected = timings.iter().enumerate().filter(|&(i, _)| i != selected_timing);
+        for (_, timing) in unselected {
+            let shape = StrokeShape::of(timing);
+            let radius = pitch_radius * shape.outer_radius;
+            layers.push(Layer::Pie { shape, radius, colour: DARK_GREEN });
+        }
+        let shape = StrokeShape::of(&timings[selected_timing]);
+        let (outer, inner) = (pitch_radius * shape.outer_radius, pitch_radius * shape.inner_radius);
+        layers.push(Layer::Pie { shape, radius: outer, colour: STROKE_MAX });
+        layers.push(Layer::Pie { shape, radius: inner, colour: STROKE_MIN });
+    }
+
+    let centre = size as f64 / 2.0;
+    for y in 0..size {
+        for x in 0..size {
+            let mut total = [0u32; 3];
+            for sy in 0..SUPERSAMPLING {
+                for sx in 0..SUPERSAMPLING {
+                    let sample_x = x as f64 + (sx as f64 + 0.5) / SUPERSAMPLING as f64;
+                    let sample_y = y as f64 + (sy as f64 + 0.5) / SUPERSAMPLING as f64;
+                    let (angle, distance) = polar_point(centre, centre, sample_x, sample_y);
+                    let colour = layers
+                        .iter()
+                        .rev()
+                        .find_map(|l| l.colour_at(angle, distance))
+                        .unwrap_or(BACKGROUND);
+                    for (t, &c) in total.iter_mut().zip(colour.iter()) {
+                        *t += c as u32;
+                    }
+                }
+            }
+            let samples = SUPERSAMPLING * SUPERSAMPLING;
+            let colour = [
+                (total[0] / samples) as u8,
+                (total[1] / samples) as u8,
+                (total[2] / samples) as u8,
+            ];
+            image.set(left + x, top + y, colour);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn draws_the_pitch_inside_the_background() {
+        let image = render_stroke(None, 2, false, 100);
+        let pixel = |x: u32, y: u32| {
+            let i = (y as usize * image.width as usize + x as usize) * 3;
+            [image.pixels[i], image.pixels[i + 1], image.pixels[i + 2]]
+        };
+        assert_eq!(pixel(0, 0), BACKGROUND);
+        assert_eq!(pixel(50, 50), GREEN);
+    }
+
+    #[test]
+    fn encodes_png() {
+        let mut png = Vec::new();
+        render_stroke(None, 2, false, 20).write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn limits_contact_sheets_to_the_size_in_the_usage_text() {
+        let pixels = |(width, height): (u32, u32)| u64::from(width) * u64::from(height);
+        assert!(pixels(contact_sheet_dimensions(1295)) <= MAX_PIXELS);
+        assert!(pixels(contact_sheet_dimensions(1296)) > MAX_PIXELS);
+    }
+}
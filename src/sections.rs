@@ -0,0 +1,189 @@
+//! Every entry of List.txt and every block of the cfg file, not just the strokes, so that the
+//! other sections can be browsed as text.
+
+use crate::document::{CfgDocument, Span};
+use crate::list_file::{Category, ListEntry};
+use crate::offsets::LocatedStroke;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// the List.txt entry, or `None` for a block that no entry points at
+    pub entry: Option<ListEntry>,
+    /// the index of the entry among the strokes, as in `CfgData::cfg_items`
+    pub stroke: Option<usize>,
+    /// the block in the cfg file, if one was found
+    pub block: Option<Span>,
+    /// 1-based line of the start of the block
+    pub line_number: Option<usize>,
+    /// the first word of the block, e.g. `BowlingTypes`
+    pub header: Option<String>,
+}
+
+impl fmt::Display for Section {
+    /// e.g. `bowling tactic: Slow (ODI) normal1`, `bowling type: Bowl fast (no block)` for an
+    /// entry whose block wasn't found, or `unlisted: BowlingTypes` for a block that isn't in
+    /// List.txt
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.entry {
+            Some(entry) => {
+                write!(f, "{}: {}", entry.category, entry.description.trim())?;
+                if self.block.is_none() {
+                    f.write_str(" (no block)")?;
+                }
+                Ok(())
+            }
+            None => write!(f, "unlisted: {}", self.header.as_deref().unwrap_or("(no header)")),
+        }
+    }
+}
+
+/// Finds the block for every List.txt entry, in List.txt order, followed by the blocks that no
+/// entry points at, in file order. Strokes get the blocks `locate_strokes` matched to them, which
+/// follow hand edits. The rest of the AI data is packed in front of the strokes rather than laid
+/// out at its List.txt spacing: the slots of those entries add up to 272384 bytes, but the first
+/// stroke starts 31840 bytes into an unmodified file. So their offsets are matched in order to
+/// the blocks before the first stroke, one block per offset, and entries sharing an offset share
+/// a block. Screens aren't exported, so they have no block.
+pub fn locate_sections(
+    document: &CfgDocument,
+    entries: &[ListEntry],
+    strokes: &[ListEntry],
+    stroke_locations: &[Option<LocatedStroke>],
+) -> Vec<Section> {
+    let blocks = document.blocks();
+    let header = |span: Span| {
+        let block = blocks.iter().find(|b| b.span == span)?;
+        Some(document.text_lossy(block.header()?).into_owned())
+    };
+    let section = |entry: Option<&ListEntry>, stroke: Option<usize>, block: Option<Span>| Section {
+        entry: entry.cloned(),
+        stroke,
+        block,
+        line_number: block.map(|b| document.location(b.start).line),
+        header: block.and_then(header),
+    };
+
+    let first_stroke = document.stroke_blocks().next().map_or(usize::MAX, |b| b.span.start);
+    let leading_blocks: Vec<Span> = blocks
+        .iter()
+        .filter(|b| b.span.start < first_stroke && b.header().is_some())
+        .map(|b| b.span)
+        .collect();
+    let mut offsets: Vec<i64> = entries
+        .iter()
+        .filter(|e| e.category != Category::Stroke && !e.category.is_screen())
+        .map(|e| e.offset)
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+
+    let mut sections: Vec<Section> = entries
+        .iter()
+        .map(|entry| match strokes.iter().position(|s| s.line_number == entry.line_number) {
+            Some(i) => section(Some(entry), Some(i), stroke_locations[i].map(|s| s.span)),
+            None if entry.category.is_screen() => section(Some(entry), None, None),
+            None => {
+                let block = offsets.binary_search(&entry.offset).ok();
+                section(Some(entry), None, block.and_then(|i| leading_blocks.get(i).copied()))
+            }
+        })
+        .collect();
+    let unlisted: Vec<Section> = blocks
+        .iter()
+        .filter(|b| !sections.iter().any(|s| s.block == Some(b.span)))
+        .map(|b| section(None, None, Some(b.span)))
+        .collect();
+    sections.extend(unlisted);
+    sections
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::list_file::{parse_list_file, read_list_file};
+    use crate::offsets::locate_strokes;
+
+    fn sections(cfg: &[u8], entries: &[ListEntry]) -> Vec<Section> {
+        let document = CfgDocument::parse(cfg);
+        let mut strokes: Vec<_> =
+            entries.iter().filter(|e| e.category == Category::Stroke).cloned().collect();
+        strokes.sort_by_key(|e| e.offset);
+        let locations = locate_strokes(&document, &strokes);
+        locate_sections(&document, entries, &strokes, &locations.strokes)
+    }
+
+    #[test]
+    fn finds_listed_and_unlisted_blocks() {
+        let mut cfg = b"BowlingTypes\r\nFast 1 Medium 2\r\n\0Camera\r\nHeight 10\r\n\0".to_vec();
+        cfg.extend_from_slice(b"Experimental\r\n");
+        cfg.resize(0x7c60, 0);
+        cfg.extend_from_slice(b"SStrokeAttributes\r\nType Attacking");
+        cfg.resize(0x7c60 + 2048, 0);
+        let list = parse_list_file(
+            "Screen ID 0 = main menu,65146881,8\n\
+             Camera,25593857,8\n\
+             Batpad (med/fast),558891009,2\n\
+             Bowl fast,25528321,2\n",
+        );
+        let sections = sections(&cfg, &list.entries);
+
+        let names: Vec<_> = sections.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            names,
+            [
+                "screen: Screen ID 0 = main menu (no block)",
+                "camera: Camera",
+                "stroke: Batpad (med/fast)",
+                "bowling type: Bowl fast",
+                "unlisted: Experimental",
+            ]
+        );
+        assert_eq!(sections[1].line_number, Some(3));
+        assert_eq!(sections[2].stroke, Some(0));
+        assert_eq!(sections[2].header.as_deref(), Some("SStrokeAttributes"));
+        assert_eq!(sections[3].block, Some(Span { start: 0, end: 31 }));
+        assert_eq!(sections[4].line_number, Some(5));
+    }
+
+    #[test]
+    fn finds_the_blocks_of_the_bundled_list_file() {
+        let list = read_list_file(concat!(env!("CARGO_MANIFEST_DIR"), "/List.txt")).unwrap();
+        let mut offsets: Vec<_> = list
+            .entries
+            .iter()
+            .filter(|e| e.category != Category::Stroke && !e.category.is_screen())
+            .map(|e| e.offset)
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        // a cfg file laid out like an export: the other AI data packed in offset order, then
+        // every stroke in its slot
+        let mut cfg = Vec::new();
+        for offset in offsets {
+            cfg.extend_from_slice(format!("Data{}\r\nValue 1\r\n\0", offset).as_bytes());
+        }
+        cfg.resize(0x7c60, 0);
+        for _ in 0..85 {
+            let start = cfg.len();
+            cfg.extend_from_slice(b"SStrokeAttributes\r\nType Attacking");
+            cfg.resize(start + 2048, 0);
+        }
+        let sections = sections(&cfg, &list.entries);
+
+        let header = |description: &str| {
+            let section = sections
+                .iter()
+                .find(|s| s.entry.as_ref().map(|e| e.description.trim()) == Some(description));
+            section.unwrap().header.as_deref()
+        };
+        assert_eq!(header("Ball Conditions"), Some("Data25628673"));
+        assert_eq!(header("Fast (ODI) defend5"), Some("Data26480641"));
+        assert_eq!(header("Slow (ODI) normal1"), Some("Data26697729"));
+        assert_eq!(header("Slow (ODI) normal2"), Some("Data26697729"));
+        assert_eq!(header("Batpad (med/fast)"), Some("SStrokeAttributes"));
+        let mut without_block = sections.iter().filter(|s| s.block.is_none());
+        assert!(without_block.all(|s| s.entry.as_ref().unwrap().category.is_screen()));
+        assert!(sections.iter().all(|s| s.entry.is_some()));
+    }
+}
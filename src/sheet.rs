@@ -0,0 +1,188 @@
+//! Contact sheets showing many strokes at once, for reviewing a whole cfg file in one go.
+
+use crate::data::{CfgData, StrokeError, StrokeWarning};
+use crate::geometry::SHEET_COLUMNS;
+use crate::strokes::Stroke;
+use crate::svg::{render_stroke, write_stroke};
+use std::fmt::Write;
+
+const LABEL_LINES: usize = 3;
+const LABEL_LINE_HEIGHT: f64 = 13.0;
+
+pub struct SheetEntry {
+    pub label: String,
+    /// an empty pitch is drawn for a stroke that could not be parsed
+    pub stroke: Result<Stroke, StrokeError>,
+    /// what tolerant parsing accepted, empty otherwise
+    pub warnings: Vec<StrokeWarning>,
+}
+
+impl SheetEntry {
+    fn stroke(&self) -> Option<&Stroke> {
+        self.stroke.as_ref().ok()
+    }
+
+    /// the label, marked when the stroke could not be parsed
+    fn caption(&self) -> String {
+        match self.stroke {
+            Ok(_) => self.label.clone(),
+            Err(_) => format!("{} (could not be parsed)", self.label),
+        }
+    }
+}
+
+/// One entry per stroke in the cfg file, labelled like the list in the preview window. With
+/// `tolerant` the strokes are parsed with `CfgData::parse_stroke_tolerant`.
+pub fn sheet_entries(data: &CfgData, tolerant: bool) -> Vec<SheetEntry> {
+    data.cfg_items
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let (stroke, warnings) = if tolerant {
+                match data.parse_stroke_tolerant(i) {
+                    Ok((stroke, warnings)) => (Ok(stroke), warnings),
+                    Err(e) => (Err(e), Vec::new()),
+                }
+            } else {
+                (data.parse_stroke(i), Vec::new())
+            };
+            SheetEntry {
+                label: format!("{}: {}", data.line_label(i), entry.description.trim()),
+                stroke,
+                warnings,
+            }
+        })
+        .collect()
+}
+
+/// Renders every entry into a single SVG grid of `size` pixel diagrams with labels underneath
+pub fn render_sheet_svg(
+    entries: &[SheetEntry],
+    selected_timing: usize,
+    selected_6hit: bool,
+    size: u32,
+) -> String {
+    let size = size as f64;
+    let cell_height = size + LABEL_LINE_HEIGHT * LABEL_LINES as f64 + 6.0;
+    let rows = entries.len().div_ceil(SHEET_COLUMNS);
+    let (width, height) = (size * SHEET_COLUMNS as f64, cell_height * rows as f64);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" font-family="sans-serif" font-size="11">"#,
+        width, height
+    )
+    .unwrap();
+    for (i, entry) in entries.iter().enumerate() {
+        let left = size * (i % SHEET_COLUMNS) as f64;
+        let top = cell_height * (i / SHEET_COLUMNS) as f64;
+        let title = match &entry.stroke {
+            Ok(_) => entry.label.clone(),
+            Err(e) => format!("{}\n{}", entry.caption(), e),
+        };
+        writeln!(svg, "<g><title>{}</title>", escape(&title)).unwrap();
+        write_stroke(
+            &mut svg,
+            entry.stroke(),
+            selected_timing,
+            selected_6hit,
+            left + size / 2.0,
+            top + size / 2.0,
+            size,
+        );
+        let max_chars = (size / 6.5) as usize;
+        for (line, text) in wrap(&entry.caption(), max_chars).iter().take(LABEL_LINES).enumerate() {
+            writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                left + size / 2.0,
+                top + size + LABEL_LINE_HEIGHT * (line + 1) as f64,
+                escape(text)
+            )
+            .unwrap();
+        }
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Renders every entry as an HTML page of captioned diagrams that reflows to fit the window
+pub fn render_sheet_html(
+    entries: &[SheetEntry],
+    selected_timing: usize,
+    selected_6hit: bool,
+    size: u32,
+) -> String {
+    let mut html = String::new();
+    html.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Strokes</title>\n<style>\n",
+    );
+    writeln!(
+        html,
+        "body {{ font-family: sans-serif; display: flex; flex-wrap: wrap; }}\n\
+         figure {{ width: {}px; margin: 8px; text-align: center; font-size: small; }}",
+        size
+    )
+    .unwrap();
+    html.push_str("</style>\n</head>\n<body>\n");
+    for entry in entries {
+        html.push_str("<figure>\n");
+        html.push_str(&render_stroke(entry.stroke(), selected_timing, selected_6hit, size));
+        writeln!(html, "<figcaption>{}</figcaption>\n</figure>", escape(&entry.caption())).unwrap();
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// splits `text` into lines of at most `max_chars`, breaking between words where possible
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > max_chars {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wraps_labels_between_words() {
+        assert_eq!(
+            wrap("301: Cover drive attack(high follow through/weight on front foot)", 20),
+            ["301: Cover drive", "attack(high follow", "through/weight on", "front foot)"]
+        );
+    }
+
+    #[test]
+    fn escapes_labels() {
+        let error = StrokeError { location: None, message: "no block".to_string(), snippet: None };
+        let entries = [SheetEntry {
+            label: "Back Cover (& 6 hit)".to_string(),
+            stroke: Err(error),
+            warnings: Vec::new(),
+        }];
+        let svg = render_sheet_svg(&entries, 2, false, 100);
+        assert!(svg.contains("<title>Back Cover (&amp; 6 hit) (could not be parsed)\nno block"));
+        assert!(svg.contains("parsed)</text>"));
+        let html = render_sheet_html(&entries, 2, false, 100);
+        assert!(html.contains("Back Cover (&amp; 6 hit) (could not be parsed)"));
+    }
+}
@@ -1,86 +1,293 @@
+use crate::document::CfgDocument;
 use nom::{
     branch::alt,
-    bytes::{complete::tag_no_case, streaming::take_till},
+    bytes::{
+        complete::tag_no_case,
+        streaming::{take_till, take_till1},
+    },
     character::streaming::{alphanumeric1, line_ending, space1},
     combinator::{map_res, opt},
-    error::Error,
+    error::{ErrorKind, FromExternalError},
     multi::many1,
     sequence::{pair, preceded, terminated, tuple},
-    IResult, Needed,
+    IResult,
 };
-use std::str::from_utf8;
+use std::fmt;
+use std::io::{self, Write};
+use std::num::{IntErrorKind, ParseIntError};
+use std::str::{from_utf8, FromStr};
 
-use anyhow::anyhow;
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Stroke {
+    pub stroke_type: StrokeType,
+    pub edge_modifier: i64,
+    /// not every stroke has a separate 6hit edge modifier
+    pub edge_modifier6: Option<i64>,
+    pub difficulty: i64,
+    pub reward: i64,
+    pub difficulty6: i64,
+    pub reward6: i64,
+    pub edge_prob: i64,
+    pub edge_prob6: i64,
+    /// bitmask of the bowler types the stroke can be played against
+    pub bowler_types: i64,
+    pub ball_stumps: i64,
+    pub ball_batsman: i64,
+    pub ball_length: i64,
     pub timings_normal: [StrokeTiming; 5],
     pub timings_6hit: [StrokeTiming; 5],
 }
 
-#[derive(Debug, PartialEq)]
+/// One keyframe. The game stores every value as an integer; the `_f64` methods give them as
+/// floating point for drawing.
+#[derive(Debug, Clone, PartialEq)]
 pub struct StrokeTiming {
-    pub vertical: f64,
-    pub direction: f64,
-    pub direction_area: f64,
-    pub power: f64,
-    pub power_area: f64,
+    /// animation frame at which this timing window applies
+    pub frame: i64,
+    pub vertical: i64,
+    pub direction: i64,
+    pub direction_area: i64,
+    pub power: i64,
+    pub power_area: i64,
+}
+
+impl StrokeTiming {
+    pub fn vertical_f64(&self) -> f64 {
+        self.vertical as f64
+    }
+
+    pub fn direction_f64(&self) -> f64 {
+        self.direction as f64
+    }
+
+    pub fn direction_area_f64(&self) -> f64 {
+        self.direction_area as f64
+    }
+
+    pub fn power_f64(&self) -> f64 {
+        self.power as f64
+    }
+
+    pub fn power_area_f64(&self) -> f64 {
+        self.power_area as f64
+    }
+}
+
+/// Why a value couldn't be read as a number
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberError {
+    NotAWholeNumber(String),
+    TooLarge(String),
+    TooSmall(String),
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberError::NotAWholeNumber(text) => write!(f, "`{}` is not a whole number", text),
+            NumberError::TooLarge(text) => {
+                write!(f, "`{}` is larger than the maximum of {}", text, i64::MAX)
+            }
+            NumberError::TooSmall(text) => {
+                write!(f, "`{}` is smaller than the minimum of {}", text, i64::MIN)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+/// Reads a value the way the game stores it, as a signed 64-bit integer
+pub fn parse_number(text: &str) -> Result<i64, NumberError> {
+    text.parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::PosOverflow => NumberError::TooLarge(text.to_string()),
+        IntErrorKind::NegOverflow => NumberError::TooSmall(text.to_string()),
+        _ => NumberError::NotAWholeNumber(text.to_string()),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrokeType {
+    Defensive,
+    Attacking,
+    Leave,
+    /// any type we don't know about, kept verbatim
+    Other(String),
+}
+
+impl FromStr for StrokeType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "Defensive" => StrokeType::Defensive,
+            "Attacking" => StrokeType::Attacking,
+            "Leave" => StrokeType::Leave,
+            other => StrokeType::Other(other.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for StrokeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StrokeType::Defensive => "Defensive",
+            StrokeType::Attacking => "Attacking",
+            StrokeType::Leave => "Leave",
+            StrokeType::Other(other) => other,
+        })
+    }
+}
+
+/// The line terminator used when writing strokes back out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
 }
 
-type CResult<'a, T = &'a [u8]> = IResult<&'a [u8], T, Error<&'a [u8]>>;
+impl LineEnding {
+    /// guesses the line ending of a cfg file from its first line break, defaulting to CRLF as
+    /// written by Config Editor
+    pub fn detect(b: &[u8]) -> LineEnding {
+        match b.iter().position(|&c| c == b'\n') {
+            Some(i) if i == 0 || b[i - 1] != b'\r' => LineEnding::Lf,
+            _ => LineEnding::CrLf,
+        }
+    }
 
-fn kv<'a, 'b: 'a>(k: &'b str) -> impl FnMut(&'a [u8]) -> CResult<'a> {
-    preceded(pair(tag_no_case(k), space1), alphanumeric1)
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
 }
 
-fn kv_line<'a, 'b: 'a>(k: &'b str) -> impl FnMut(&'a [u8]) -> CResult<'a, f64> {
-    map_res(map_res(terminated(kv(k), many1(alt((space1, line_ending)))), from_utf8), str::parse)
+/// Where the parser stopped, and what it expected to find there once that is known
+#[derive(Debug)]
+struct Expected<'a> {
+    input: &'a [u8],
+    what: Option<String>,
+    /// what was wrong with what was there instead, if that's more than it being missing
+    problem: Option<String>,
+}
+
+impl<'a> nom::error::ParseError<&'a [u8]> for Expected<'a> {
+    fn from_error_kind(input: &'a [u8], _kind: ErrorKind) -> Self {
+        Expected { input, what: None, problem: None }
+    }
+
+    fn append(_input: &'a [u8], _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a [u8], E> for Expected<'a> {
+    fn from_external_error(input: &'a [u8], _kind: ErrorKind, _e: E) -> Self {
+        Expected { input, what: None, problem: None }
+    }
+}
+
+type CResult<'a, T = &'a [u8]> = IResult<&'a [u8], T, Expected<'a>>;
+
+/// names what `parser` was looking for if it fails without saying, pointing at where it started
+fn expect<'a, T>(
+    what: String,
+    mut parser: impl FnMut(&'a [u8]) -> CResult<'a, T>,
+) -> impl FnMut(&'a [u8]) -> CResult<'a, T> {
+    move |b| {
+        parser(b).map_err(|e| {
+            e.map(|e| match e.what {
+                Some(_) => e,
+                None => Expected { input: b, what: Some(what.clone()), problem: None },
+            })
+        })
+    }
+}
+
+fn kv<'a, 'b: 'a, T: FromStr>(k: &'b str) -> impl FnMut(&'a [u8]) -> CResult<'a, T> {
+    preceded(
+        expect(format!("`{}`", k), pair(tag_no_case(k), space1)),
+        expect("a value".to_string(), map_res(map_res(alphanumeric1, from_utf8), str::parse)),
+    )
+}
+
+/// a whole value up to the next whitespace, which must be a number
+fn number<'a>(b: &'a [u8]) -> CResult<'a, i64> {
+    let (rest, word) =
+        expect("a number".to_string(), take_till1(|c| b" \t\r\n\0".contains(&c)))(b)?;
+    match parse_number(&String::from_utf8_lossy(word)) {
+        Ok(n) => Ok((rest, n)),
+        Err(e) => Err(nom::Err::Failure(Expected {
+            input: b,
+            what: Some("a number".to_string()),
+            problem: Some(e.to_string()),
+        })),
+    }
+}
+
+fn kv_number<'a, 'b: 'a>(k: &'b str) -> impl FnMut(&'a [u8]) -> CResult<'a, i64> {
+    preceded(expect(format!("`{}`", k), pair(tag_no_case(k), space1)), number)
+}
+
+fn end_of_line<'a>() -> impl FnMut(&'a [u8]) -> CResult<'a, Vec<&'a [u8]>> {
+    expect("the end of the line".to_string(), many1(line_ending))
+}
+
+fn kv_line<'a, 'b: 'a>(k: &'b str) -> impl FnMut(&'a [u8]) -> CResult<'a, i64> {
+    terminated(
+        kv_number(k),
+        expect("the end of the line".to_string(), many1(alt((space1, line_ending)))),
+    )
 }
 
 fn kv2_line<'a, 'b: 'a>(
     k1: &'b str,
     k2: &'b str,
-) -> impl FnMut(&'a [u8]) -> CResult<'a, (f64, f64)> {
+) -> impl FnMut(&'a [u8]) -> CResult<'a, (i64, i64)> {
     terminated(
-        pair(
-            map_res(map_res(terminated(kv(k1), space1), from_utf8), str::parse),
-            map_res(map_res(kv(k2), from_utf8), str::parse),
-        ),
-        many1(line_ending),
+        pair(terminated(kv_number(k1), expect(format!("`{}`", k2), space1)), kv_number(k2)),
+        end_of_line(),
     )
 }
 
 fn parse_stroke<'a>(b: &'a [u8]) -> CResult<'a, Stroke> {
-    let (b, _) = take_till(|c| c == b't')(b)?;
-    let (b, _) = tag_no_case("trokeAttributes")(b)?;
-
-    let (b, _typ) = preceded(many1(line_ending), terminated(kv("Type"), many1(line_ending)))(b)?;
-    let (b, _edge_modifier) = kv_line("EdgeModifier")(b)?;
-    let (b, _edge_modifier6) = opt(kv_line("EdgeModifier6"))(b)?;
-    let (b, (_Difficulty, _Reward)) = kv2_line("Difficulty", "Reward")(b)?;
-    let (b, (_Difficulty6, _Reward6)) = kv2_line("Difficulty6", "Reward6")(b)?;
-    let (b, (_EdgeProb, _EdgeProb6)) = kv2_line("EdgeProb", "EdgeProb6")(b)?;
-    let (b, _BowlerTypes) = kv_line("BowlerTypes")(b)?;
-    let (b, _BallStumps) = kv_line("BallStumps")(b)?;
-    let (b, _BallBatsman) = kv_line("BallBatsman")(b)?;
-    let (b, _BallLength) = kv_line("BallLength")(b)?;
-
-    let keyframe = |mode, idx, b| -> IResult<_, _> {
-        let (b, _) = tuple((
-            tag_no_case("Mode"),
-            space1,
-            tag_no_case(mode),
-            space1,
-            tag_no_case("KeyFrame"),
-            space1,
-            tag_no_case(idx),
-            many1(line_ending),
-        ))(b)?;
-        let (b, _) = kv_line("Frame")(b)?;
+    let (b, _) = expect(
+        "`StrokeAttributes`".to_string(),
+        preceded(take_till(|c| c == b't'), tag_no_case("trokeAttributes")),
+    )(b)?;
+
+    let (b, stroke_type) = preceded(end_of_line(), terminated(kv("Type"), end_of_line()))(b)?;
+    let (b, edge_modifier) = kv_line("EdgeModifier")(b)?;
+    let (b, edge_modifier6) = opt(kv_line("EdgeModifier6"))(b)?;
+    let (b, (difficulty, reward)) = kv2_line("Difficulty", "Reward")(b)?;
+    let (b, (difficulty6, reward6)) = kv2_line("Difficulty6", "Reward6")(b)?;
+    let (b, (edge_prob, edge_prob6)) = kv2_line("EdgeProb", "EdgeProb6")(b)?;
+    let (b, bowler_types) = kv_line("BowlerTypes")(b)?;
+    let (b, ball_stumps) = kv_line("BallStumps")(b)?;
+    let (b, ball_batsman) = kv_line("BallBatsman")(b)?;
+    let (b, ball_length) = kv_line("BallLength")(b)?;
+
+    let keyframe = |mode, idx, b| -> CResult<_> {
+        let (b, _) = expect(
+            format!("`Mode {} KeyFrame {}`", mode, idx),
+            tuple((
+                tag_no_case("Mode"),
+                space1,
+                tag_no_case(mode),
+                space1,
+                tag_no_case("KeyFrame"),
+                space1,
+                tag_no_case(idx),
+            )),
+        )(b)?;
+        let (b, _) = end_of_line()(b)?;
+        let (b, frame) = kv_line("Frame")(b)?;
         let (b, vertical) = kv_line("Vertical")(b)?;
         let (b, (direction, direction_area)) = kv2_line("Direction", "DirectionArea")(b)?;
         let (b, (power, power_area)) = kv2_line("Power", "PowerArea")(b)?;
-        Ok((b, StrokeTiming { vertical, direction, direction_area, power, power_area }))
+        Ok((b, StrokeTiming { frame, vertical, direction, direction_area, power, power_area }))
     };
 
     let (b, normal_0) = keyframe("Normal", "0", b)?;
@@ -98,39 +305,140 @@ fn parse_stroke<'a>(b: &'a [u8]) -> CResult<'a, Stroke> {
     Ok((
         b,
         Stroke {
+            stroke_type,
+            edge_modifier,
+            edge_modifier6,
+            difficulty,
+            reward,
+            difficulty6,
+            reward6,
+            edge_prob,
+            edge_prob6,
+            bowler_types,
+            ball_stumps,
+            ball_batsman,
+            ball_length,
             timings_normal: [normal_0, normal_1, normal_2, normal_3, normal_4],
             timings_6hit: [hit6_0, hit6_1, hit6_2, hit6_3, hit6_4],
         },
     ))
 }
 
+/// Why a stroke could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// how far into the parsed bytes the problem is
+    pub offset: usize,
+    /// what the parser was looking for, e.g. "`DirectionArea`"
+    pub expected: Option<String>,
+    /// what was expected and where, e.g. "expected `DirectionArea` after `Direction` in Mode 6Hit
+    /// KeyFrame 3"
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(
+        b: &[u8],
+        offset: usize,
+        expected: Option<String>,
+        problem: Option<String>,
+    ) -> ParseError {
+        let what = expected.as_deref().unwrap_or("something else");
+        let mut message = format!("expected {}", what);
+        // name the key before the problem, and the keyframe it's in
+        let before = CfgDocument::parse(&b[..offset]);
+        let entries: Vec<_> =
+            before.blocks().last().into_iter().flat_map(|b| b.entries()).collect();
+        if let Some(previous) = entries.last() {
+            message += &format!(" after `{}`", before.text_lossy(previous.key));
+        }
+        let is_key = |i: usize, key: &[u8]| {
+            matches!(
+                entries.get(i),
+                Some(e) if before.text(e.key).eq_ignore_ascii_case(key)
+            )
+        };
+        let mode =
+            (0..entries.len()).rev().find(|&i| is_key(i, b"Mode") && is_key(i + 1, b"KeyFrame"));
+        if let Some(i) = mode {
+            let value =
+                |i: usize| entries[i].value.map(|v| before.text_lossy(v)).unwrap_or_default();
+            message += &format!(" in Mode {} KeyFrame {}", value(i), value(i + 1));
+        }
+        if let Some(problem) = problem {
+            message += &format!(", but {}", problem);
+        }
+        ParseError { offset, expected, message }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl Stroke {
-    pub fn parse(b: &[u8]) -> anyhow::Result<Stroke> {
+    pub fn parse(b: &[u8]) -> Result<Stroke, ParseError> {
         let (_remaining, stroke) = parse_stroke(b).map_err(|e| match e {
-            nom::Err::Incomplete(Needed::Size(u)) => {
-                anyhow!("Error parsing stroke: parsing requires {} bytes", u)
-            }
-            nom::Err::Incomplete(Needed::Unknown) => {
-                anyhow!("Error parsing stroke: parsing requires more data")
-            }
-            nom::Err::Failure(v) | nom::Err::Error(v) => {
-                let input = String::from_utf8_lossy(v.input);
-                let input_slice =
-                    input.char_indices().nth(10).map(|(i, _)| &input[..i]).unwrap_or(&input);
-                anyhow!("Error parsing stroke: {:?} at {:?}", v.code, input_slice)
+            nom::Err::Incomplete(_) => ParseError {
+                offset: b.len(),
+                expected: None,
+                message: "the stroke ends too early".to_string(),
+            },
+            nom::Err::Failure(e) | nom::Err::Error(e) => {
+                ParseError::new(b, b.len() - e.input.len(), e.what, e.problem)
             }
         })?;
         Ok(stroke)
     }
+
+    /// Writes the stroke as a `StrokeAttributes` block in the AI.cfg layout, starting at the
+    /// `StrokeAttributes` header and ending with the line break after the last keyframe.
+    pub fn write<W: Write>(&self, w: &mut W, line_ending: LineEnding) -> io::Result<()> {
+        let nl = line_ending.as_str();
+        write!(w, "StrokeAttributes{}{}", nl, nl)?;
+        write!(w, "Type {}{}", self.stroke_type, nl)?;
+        write!(w, "EdgeModifier {}{}", self.edge_modifier, nl)?;
+        if let Some(edge_modifier6) = self.edge_modifier6 {
+            write!(w, "EdgeModifier6 {}{}", edge_modifier6, nl)?;
+        }
+        write!(w, "Difficulty {} Reward {}{}", self.difficulty, self.reward, nl)?;
+        write!(w, "Difficulty6 {} Reward6 {}{}", self.difficulty6, self.reward6, nl)?;
+        write!(w, "EdgeProb {} EdgeProb6 {}{}", self.edge_prob, self.edge_prob6, nl)?;
+        write!(w, "BowlerTypes {}{}", self.bowler_types, nl)?;
+        write!(w, "BallStumps {}{}", self.ball_stumps, nl)?;
+        write!(w, "BallBatsman {}{}", self.ball_batsman, nl)?;
+        write!(w, "BallLength {}{}", self.ball_length, nl)?;
+
+        for (mode, timings) in
+            [("Normal", &self.timings_normal), ("6Hit", &self.timings_6hit)].iter()
+        {
+            for (i, t) in timings.iter().enumerate() {
+                write!(w, "{}Mode {} KeyFrame {}{}", nl, mode, i, nl)?;
+                write!(w, "Frame {}{}", t.frame, nl)?;
+                write!(w, "Vertical {}{}", t.vertical, nl)?;
+                write!(w, "Direction {} DirectionArea {}{}", t.direction, t.direction_area, nl)?;
+                write!(w, "Power {} PowerArea {}{}", t.power, t.power_area, nl)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_cfg_bytes(&self, line_ending: LineEnding) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, line_ending).expect("writing to a Vec cannot fail");
+        bytes
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    #[test]
-    fn parses_stroke_successfully() {
-        let stroke = b"\0\0SStrokeAttributes
+    const STROKE: &[u8] = b"\0\0SStrokeAttributes
 
 Type Defensive
 EdgeModifier 10
@@ -204,83 +512,217 @@ Direction 242980370 DirectionArea 24298037
 Power 144631 PowerArea 361578
 \0\0";
 
+    /// the part of the fixture a stroke serialises to, without the NUL padding either side
+    fn stroke_block(b: &[u8]) -> &[u8] {
+        let start = b.windows(16).position(|w| w == b"StrokeAttributes").unwrap();
+        let end = b.iter().rposition(|&c| c != 0).unwrap() + 1;
+        &b[start..end]
+    }
+
+    fn round_trip(b: &[u8]) {
+        let line_ending = LineEnding::detect(b);
+        let stroke = Stroke::parse(b).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&stroke.to_cfg_bytes(line_ending)),
+            String::from_utf8_lossy(stroke_block(b))
+        );
+    }
+
+    #[test]
+    fn parses_stroke_successfully() {
         let expected = Stroke {
+            stroke_type: StrokeType::Defensive,
+            edge_modifier: 10,
+            edge_modifier6: Some(10),
+            difficulty: 0,
+            reward: 0,
+            difficulty6: 0,
+            reward6: 0,
+            edge_prob: 0,
+            edge_prob6: 0,
+            bowler_types: 15,
+            ball_stumps: 3,
+            ball_batsman: 3,
+            ball_length: 2,
             timings_normal: [
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 10,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 14,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 18,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 21,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 24,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
             ],
             timings_6hit: [
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 10,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 14,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 18,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 21,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
                 StrokeTiming {
-                    vertical: 90596966.0,
-                    direction: 242980370.0,
-                    direction_area: 24298037.0,
-                    power: 144631.0,
-                    power_area: 361578.0,
+                    frame: 24,
+                    vertical: 90596966,
+                    direction: 242980370,
+                    direction_area: 24298037,
+                    power: 144631,
+                    power_area: 361578,
                 },
             ],
         };
 
-        assert_eq!(Stroke::parse(stroke).unwrap(), expected);
+        assert_eq!(Stroke::parse(STROKE).unwrap(), expected);
+    }
+
+    #[test]
+    fn writes_stroke_back_identically() {
+        round_trip(STROKE);
+    }
+
+    #[test]
+    fn writes_crlf_stroke_back_identically() {
+        let crlf = String::from_utf8_lossy(STROKE).replace('\n', "\r\n");
+        assert_eq!(LineEnding::detect(crlf.as_bytes()), LineEnding::CrLf);
+        round_trip(crlf.as_bytes());
+    }
+
+    #[test]
+    fn writes_stroke_without_edge_modifier6_back_identically() {
+        let without = String::from_utf8_lossy(STROKE).replace("EdgeModifier6 10\n", "");
+        assert_eq!(Stroke::parse(without.as_bytes()).unwrap().edge_modifier6, None);
+        round_trip(without.as_bytes());
+    }
+
+    #[test]
+    fn edited_stroke_parses_back_to_the_edit() {
+        let mut stroke = Stroke::parse(STROKE).unwrap();
+        stroke.stroke_type = StrokeType::Attacking;
+        stroke.timings_6hit[3].direction_area = 30000000;
+        let mut written = stroke.to_cfg_bytes(LineEnding::CrLf);
+        // the parser is streaming, so it needs to see the padding after the block
+        written.extend_from_slice(b"\0\0");
+        assert_eq!(Stroke::parse(&written).unwrap(), stroke);
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        let broken = String::from_utf8_lossy(STROKE).replace("BallStumps", "BallStump");
+        let error = Stroke::parse(broken.as_bytes()).unwrap_err();
+        assert_eq!(&broken.as_bytes()[error.offset..error.offset + 9], b"BallStump");
+        assert_eq!(error.message, "expected `BallStumps` after `BowlerTypes`");
+    }
+
+    #[test]
+    fn names_the_keyframe_and_the_key_before_the_problem() {
+        let stroke = String::from_utf8_lossy(STROKE);
+        // breaks the `n`th occurrence of `from`
+        let expected_after = |n: usize, from: &str, to: &str| {
+            let (i, _) = stroke.match_indices(from).nth(n).unwrap();
+            let broken = format!("{}{}{}", &stroke[..i], to, &stroke[i + from.len()..]);
+            Stroke::parse(broken.as_bytes()).unwrap_err().message
+        };
+        assert_eq!(
+            expected_after(8, "Direction 242980370 DirectionArea", "Direction 242980370 Area"),
+            "expected `DirectionArea` after `Direction` in Mode 6Hit KeyFrame 3"
+        );
+        assert_eq!(
+            expected_after(0, "Frame 21", "Frame 21.5"),
+            "expected a number after `Frame` in Mode Normal KeyFrame 3, but `21.5` is not a whole \
+             number"
+        );
+        assert_eq!(
+            expected_after(0, "Mode Normal KeyFrame 2", "Mode Normal KeyFrame 3"),
+            "expected `Mode Normal KeyFrame 2` after `PowerArea` in Mode Normal KeyFrame 1"
+        );
+        assert_eq!(
+            expected_after(0, "Type Defensive", "Type"),
+            "expected `Type` after `SStrokeAttributes`"
+        );
+    }
+
+    #[test]
+    fn parses_signed_values_and_rejects_out_of_range_ones() {
+        let stroke = String::from_utf8_lossy(STROKE);
+        let signed = stroke.replacen("Vertical 90596966", "Vertical -90596966", 1);
+        assert_eq!(Stroke::parse(signed.as_bytes()).unwrap().timings_normal[0].vertical, -90596966);
+
+        let huge = stroke.replacen("Power 144631", "Power 9223372036854775808", 1);
+        let error = Stroke::parse(huge.as_bytes()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "expected a number after `Power` in Mode Normal KeyFrame 0, but `9223372036854775808` \
+             is larger than the maximum of 9223372036854775807"
+        );
+        assert!(huge[error.offset..].starts_with("9223372036854775808"));
+    }
+
+    #[test]
+    fn reads_numbers_as_signed_64_bit_integers() {
+        assert_eq!(parse_number("-12"), Ok(-12));
+        assert_eq!(parse_number("+12"), Ok(12));
+        assert_eq!(parse_number("12abc"), Err(NumberError::NotAWholeNumber("12abc".to_string())));
+        assert_eq!(
+            parse_number("-9223372036854775809"),
+            Err(NumberError::TooSmall("-9223372036854775809".to_string()))
+        );
     }
 }
@@ -0,0 +1,275 @@
+//! Renders the same pitch diagram as the preview window to SVG, so it can be shared outside the
+//! app.
+
+use crate::geometry::{
+    screen_point, Rgb, StrokeShape, DARK_GREEN, GREEN, OVERLAY, PITCH_PADDING, STROKE_MAX,
+    STROKE_MIN,
+};
+use crate::strokes::{Stroke, StrokeTiming};
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+/// Renders the pitch and the five timings of one mode of `stroke`, with `selected_timing`
+/// highlighted, as a standalone SVG document `size` pixels square.
+pub fn render_stroke(
+    stroke: Option<&Stroke>,
+    selected_timing: usize,
+    selected_6hit: bool,
+    size: u32,
+) -> String {
+    render_stroke_with_overlay(stroke, None, selected_timing, selected_6hit, size)
+}
+
+/// Like `render_stroke`, with the selected timing of `overlay` outlined on top, to show how it
+/// differs from `stroke`
+pub fn render_stroke_with_overlay(
+    stroke: Option<&Stroke>,
+    overlay: Option<&Stroke>,
+    selected_timing: usize,
+    selected_6hit: bool,
+    size: u32,
+) -> String {
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+        size
+    )
+    .unwrap();
+    write_stroke(
+        &mut svg,
+        stroke,
+        selected_timing,
+        selected_6hit,
+        size as f64 / 2.0,
+        size as f64 / 2.0,
+        size as f64,
+    );
+    if let Some(overlay) = overlay {
+        let timings = if selected_6hit { &overlay.timings_6hit } else { &overlay.timings_normal };
+        let centre = size as f64 / 2.0;
+        let pitch_radius = centre - PITCH_PADDING;
+        write_outline(
+            &mut svg,
+            centre,
+            centre,
+            pitch_radius,
+            &StrokeShape::of(&timings[selected_timing]),
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes the diagram elements for a `size` square centred on `(centre_x, centre_y)`
+pub(crate) fn write_stroke(
+    svg: &mut String,
+    stroke: Option<&Stroke>,
+    selected_timing: usize,
+    selected_6hit: bool,
+    centre_x: f64,
+    centre_y: f64,
+    size: f64,
+) {
+    let pitch_radius = size / 2.0 - PITCH_PADDING;
+    writeln!(
+        svg,
+        r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}" stroke="white"/>"#,
+        centre_x,
+        centre_y,
+        pitch_radius,
+        css(GREEN)
+    )
+    .unwrap();
+
+    if let Some(stroke) = stroke {
+        let timings = if selected_6hit { &stroke.timings_6hit } else { &stroke.timings_normal };
+        for (i, timing) in timings.iter().enumerate() {
+            if i != selected_timing {
+                write_stroke_segment(svg, timing, centre_x, centre_y, pitch_radius, false);
+            }
+        }
+        write_stroke_segment(
+            svg,
+            &timings[selected_timing],
+            centre_x,
+            centre_y,
+            pitch_radius,
+            true,
+        );
+    }
+}
+
+fn write_stroke_segment(
+    svg: &mut String,
+    stroke: &StrokeTiming,
+    centre_x: f64,
+    centre_y: f64,
+    pitch_radius: f64,
+    highlighted: bool,
+) {
+    let shape = StrokeShape::of(stroke);
+    for &(radius, colour) in
+        [(shape.outer_radius, STROKE_MAX), (shape.inner_radius, STROKE_MIN)].iter()
+    {
+        let fill = if highlighted { colour } else { DARK_GREEN };
+        write_pie(svg, centre_x, centre_y, pitch_radius * radius, &shape, fill);
+    }
+}
+
+/// Writes the pie of `shape` at the given radius
+fn write_pie(
+    svg: &mut String,
+    centre_x: f64,
+    centre_y: f64,
+    radius: f64,
+    shape: &StrokeShape,
+    fill: Rgb,
+) {
+    let fill = css(fill);
+    if shape.is_full_circle() {
+        writeln!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#,
+            centre_x, centre_y, radius, fill
+        )
+        .unwrap();
+        return;
+    }
+    let (start_x, start_y) = screen_point(centre_x, centre_y, radius, shape.min_angle);
+    let (end_x, end_y) = screen_point(centre_x, centre_y, radius, shape.max_angle);
+    // anticlockwise on screen is SVG's negative sweep direction
+    writeln!(
+        svg,
+        r#"<path d="M {:.1} {:.1} L {:.1} {:.1} A {:.1} {:.1} 0 {} 0 {:.1} {:.1} Z" fill="{}"/>"#,
+        centre_x,
+        centre_y,
+        start_x,
+        start_y,
+        radius,
+        radius,
+        (shape.sweep() > PI) as u8,
+        end_x,
+        end_y,
+        fill
+    )
+    .unwrap();
+}
+
+/// Outlines the window between the inner and outer radius of `shape`, without filling it
+fn write_outline(
+    svg: &mut String,
+    centre_x: f64,
+    centre_y: f64,
+    pitch_radius: f64,
+    shape: &StrokeShape,
+) {
+    let (inner, outer) = (pitch_radius * shape.inner_radius, pitch_radius * shape.outer_radius);
+    let style = format!(r#"fill="none" stroke="{}" stroke-width="2""#, css(OVERLAY));
+    if shape.is_full_circle() {
+        for &radius in [outer, inner].iter().filter(|&&r| r > 0.0) {
+            writeln!(
+                svg,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" {}/>"#,
+                centre_x, centre_y, radius, style
+            )
+            .unwrap();
+        }
+        return;
+    }
+    let point = |radius, angle| screen_point(centre_x, centre_y, radius, angle);
+    let large_arc = (shape.sweep() > PI) as u8;
+    let (outer_start, outer_end) = (point(outer, shape.min_angle), point(outer, shape.max_angle));
+    let (inner_start, inner_end) = (point(inner, shape.min_angle), point(inner, shape.max_angle));
+    let mut d = format!(
+        "M {:.1} {:.1} L {:.1} {:.1} A {:.1} {:.1} 0 {} 0 {:.1} {:.1} L {:.1} {:.1}",
+        inner_start.0,
+        inner_start.1,
+        outer_start.0,
+        outer_start.1,
+        outer,
+        outer,
+        large_arc,
+        outer_end.0,
+        outer_end.1,
+        inner_end.0,
+        inner_end.1
+    );
+    // back along the inner arc clockwise, unless the window reaches the centre
+    if inner > 0.0 {
+        write!(
+            d,
+            " A {:.1} {:.1} 0 {} 1 {:.1} {:.1}",
+            inner, inner, large_arc, inner_start.0, inner_start.1
+        )
+        .unwrap();
+    }
+    writeln!(svg, r#"<path d="{} Z" {}/>"#, d, style).unwrap();
+}
+
+/// `colour` as a CSS colour
+fn css([red, green, blue]: Rgb) -> String {
+    format!("rgb({},{},{})", red, green, blue)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn shape(min_angle: f64, max_angle: f64) -> StrokeShape {
+        StrokeShape {
+            centre_angle: 0.0,
+            min_angle,
+            max_angle,
+            inner_radius: 0.0,
+            outer_radius: 1.0,
+        }
+    }
+
+    #[test]
+    fn draws_a_narrow_pie_anticlockwise_from_the_min_angle() {
+        let mut svg = String::new();
+        write_pie(&mut svg, 100.0, 100.0, 50.0, &shape(0.0, PI / 2.0), [255, 0, 0]);
+        assert_eq!(
+            svg,
+            "<path d=\"M 100.0 100.0 L 100.0 50.0 A 50.0 50.0 0 0 0 50.0 100.0 Z\" fill=\"rgb(255,0,0)\"/>\n"
+        );
+    }
+
+    #[test]
+    fn wraps_angles_and_draws_full_circles_like_gdi() {
+        let mut svg = String::new();
+        write_pie(&mut svg, 100.0, 100.0, 50.0, &shape(-PI / 2.0, PI), [255, 0, 0]);
+        assert!(svg.contains(" 0 1 0 "));
+
+        let mut svg = String::new();
+        write_pie(&mut svg, 100.0, 100.0, 50.0, &shape(1.0, 1.0), [255, 0, 0]);
+        assert!(svg.starts_with("<circle"));
+    }
+
+    #[test]
+    fn outlines_the_power_window_of_an_overlay() {
+        let mut svg = String::new();
+        let window = StrokeShape { inner_radius: 0.5, ..shape(0.0, PI / 2.0) };
+        write_outline(&mut svg, 100.0, 100.0, 50.0, &window);
+        assert_eq!(
+            svg,
+            "<path d=\"M 100.0 75.0 L 100.0 50.0 A 50.0 50.0 0 0 0 50.0 100.0 L 75.0 100.0 \
+             A 25.0 25.0 0 0 1 100.0 75.0 Z\" fill=\"none\" stroke=\"rgb(0,60,255)\" \
+             stroke-width=\"2\"/>\n"
+        );
+
+        let mut svg = String::new();
+        write_outline(&mut svg, 100.0, 100.0, 50.0, &shape(1.0, 1.0));
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains("fill=\"none\""));
+    }
+
+    #[test]
+    fn renders_an_empty_pitch_without_a_stroke() {
+        let svg = render_stroke(None, 2, false, 200);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(!svg.contains("<path"));
+    }
+}
@@ -0,0 +1,34 @@
+//! A stroke for the tests of every module to start from, with the values of a shipped stroke.
+
+use crate::strokes::Stroke;
+use crate::tolerant::MODES;
+
+/// the header of the stroke, with `\n` line endings
+pub const HEADER: &str = "Type Attacking\nEdgeModifier 10\nDifficulty 0 Reward 0\n\
+    Difficulty6 0 Reward6 0\nEdgeProb 0 EdgeProb6 0\nBowlerTypes 15\nBallStumps 3\n\
+    BallBatsman 3\nBallLength 2\n";
+
+/// each keyframe of the stroke, with `{frame}` standing for its Frame
+pub const KEYFRAME: &str = "Frame {frame}\nVertical 90596966\n\
+    Direction 242980370 DirectionArea 24298037\nPower 144631 PowerArea 361578\n";
+
+/// the Frame of each keyframe, the same in both modes
+pub const FRAMES: [i64; 5] = [10, 14, 18, 21, 24];
+
+/// a stroke block with `header` and `keyframe` for every keyframe, laid out as Config Editor
+/// exports it with `\r\n` line endings and the NUL that ends the block
+pub fn stroke_text(header: &str, keyframe: &str) -> String {
+    let mut text = format!("SStrokeAttributes\n\n{}", header);
+    for mode in MODES.iter() {
+        for (i, frame) in FRAMES.iter().enumerate() {
+            let keyframe = keyframe.replace("{frame}", &frame.to_string());
+            text += &format!("\nMode {} KeyFrame {}\n{}", mode, i, keyframe);
+        }
+    }
+    format!("{}\0", text.replace('\n', "\r\n"))
+}
+
+/// the stroke itself
+pub fn stroke() -> Stroke {
+    Stroke::parse(stroke_text(HEADER, KEYFRAME).as_bytes()).unwrap()
+}
@@ -0,0 +1,434 @@
+//! A lenient reader for stroke blocks that have been edited by hand.
+//!
+//! Unlike `Stroke::parse`, which follows the exact layout Config Editor writes, this works from
+//! the key/value entries of a `CfgDocument`, so keys can come in any order within the header or a
+//! keyframe, values can be signed and spacing and `//` comments don't matter. Anything that
+//! differs from what `Stroke::write` produces is reported as a warning rather than an error.
+
+use crate::document::{Block, CfgDocument, Entry, Line, Span, TokenKind};
+use crate::strokes::{parse_number, LineEnding, ParseError, Stroke, StrokeTiming, StrokeType};
+use std::collections::HashMap;
+
+pub const HEADER_KEYS: [&str; 13] = [
+    "Type",
+    "EdgeModifier",
+    "EdgeModifier6",
+    "Difficulty",
+    "Reward",
+    "Difficulty6",
+    "Reward6",
+    "EdgeProb",
+    "EdgeProb6",
+    "BowlerTypes",
+    "BallStumps",
+    "BallBatsman",
+    "BallLength",
+];
+pub const KEYFRAME_KEYS: [&str; 6] =
+    ["Frame", "Vertical", "Direction", "DirectionArea", "Power", "PowerArea"];
+pub const MODES: [&str; 2] = ["Normal", "6Hit"];
+
+/// Something in a stroke block that was accepted but isn't laid out the way Config Editor would
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    /// how far into the parsed bytes it is
+    pub offset: usize,
+    pub message: String,
+}
+
+/// The stroke header, or one keyframe: the lines from a `Mode` line up to the next one
+struct Section<'d> {
+    /// `None` for the header, otherwise the mode and keyframe as written
+    keyframe: Option<(String, String)>,
+    lines: Vec<&'d Line>,
+}
+
+impl Section<'_> {
+    fn name(&self) -> String {
+        match &self.keyframe {
+            Some((mode, keyframe)) => format!("Mode {} KeyFrame {}", mode, keyframe),
+            None => "the stroke header".to_string(),
+        }
+    }
+
+    fn start(&self) -> usize {
+        self.lines.first().map_or(0, |l| l.span.start)
+    }
+
+    fn end(&self) -> usize {
+        self.lines.last().map_or(0, |l| l.span.end)
+    }
+
+    /// the key/value entries, leaving out the `Mode` and `KeyFrame` of a keyframe
+    fn fields(&self) -> impl Iterator<Item = &Entry> {
+        let skip = if self.keyframe.is_some() { 2 } else { 0 };
+        self.lines.iter().flat_map(|l| l.entries.iter()).skip(skip)
+    }
+}
+
+/// splits a stroke block, after its `StrokeAttributes` line, into the header and keyframes
+fn split_sections<'d>(document: &CfgDocument, block: &'d Block) -> Vec<Section<'d>> {
+    let mut sections = vec![Section { keyframe: None, lines: Vec::new() }];
+    for line in block.lines.iter().skip(1).filter(|l| !l.entries.is_empty()) {
+        let key = |i: usize| line.entries.get(i).map(|e| document.text(e.key));
+        let value = |i: usize| {
+            line.entries[i].value.map(|v| document.text_lossy(v).into_owned()).unwrap_or_default()
+        };
+        if matches!(key(0), Some(k) if k.eq_ignore_ascii_case(b"Mode")) {
+            let keyframe = match key(1) {
+                Some(k) if k.eq_ignore_ascii_case(b"KeyFrame") => value(1),
+                _ => String::new(),
+            };
+            sections.push(Section { keyframe: Some((value(0), keyframe)), lines: Vec::new() });
+        }
+        sections.last_mut().expect("there is always a header").lines.push(line);
+    }
+    sections
+}
+
+/// The fields of one section, by lowercased key
+struct Fields<'s, 'a> {
+    document: &'s CfgDocument<'a>,
+    section: &'s Section<'s>,
+    entries: HashMap<String, &'s Entry>,
+}
+
+impl<'s, 'a> Fields<'s, 'a> {
+    fn new(
+        document: &'s CfgDocument<'a>,
+        section: &'s Section<'s>,
+        known: &[&str],
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Fields<'s, 'a> {
+        let mut entries = HashMap::new();
+        for entry in section.fields() {
+            let key = document.text_lossy(entry.key);
+            let offset = entry.key.start;
+            if !known.iter().any(|k| k.eq_ignore_ascii_case(&key)) {
+                let message = format!("ignored unknown key `{}` in {}", key, section.name());
+                warnings.push(ParseWarning { offset, message });
+            } else if entries.insert(key.to_ascii_lowercase(), entry).is_some() {
+                let message =
+                    format!("`{}` appears twice in {}, using the last one", key, section.name());
+                warnings.push(ParseWarning { offset, message });
+            }
+        }
+        Fields { document, section, entries }
+    }
+
+    /// the text of the value of `key`, if it's there
+    fn text(&self, key: &str) -> Result<Option<(Span, String)>, ParseError> {
+        let entry = match self.entries.get(&key.to_ascii_lowercase()) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let value = entry.value.ok_or_else(|| ParseError {
+            offset: entry.key.end,
+            expected: Some("a value".to_string()),
+            message: format!("expected a value after `{}` in {}", key, self.section.name()),
+        })?;
+        Ok(Some((value, self.document.text_lossy(value).into_owned())))
+    }
+
+    fn optional(&self, key: &str) -> Result<Option<i64>, ParseError> {
+        let (value, text) = match self.text(key)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        parse_number(&text).map(Some).map_err(|e| ParseError {
+            offset: value.start,
+            expected: Some("a number".to_string()),
+            message: format!(
+                "expected a number after `{}` in {}, but {}",
+                key,
+                self.section.name(),
+                e
+            ),
+        })
+    }
+
+    fn missing(&self, key: &str) -> ParseError {
+        ParseError {
+            offset: self.section.end(),
+            expected: Some(format!("`{}`", key)),
+            message: format!("missing `{}` in {}", key, self.section.name()),
+        }
+    }
+
+    fn required(&self, key: &str) -> Result<i64, ParseError> {
+        self.optional(key)?.ok_or_else(|| self.missing(key))
+    }
+
+    fn stroke_type(&self) -> Result<StrokeType, ParseError> {
+        let (_, text) = self.text("Type")?.ok_or_else(|| self.missing("Type"))?;
+        Ok(text.parse().unwrap_or_else(|never| match never {}))
+    }
+}
+
+/// Reads the first stroke block in `b`, accepting any key order, signed values, irregular
+/// spacing and comments, and reporting each of those as a warning.
+pub fn parse_stroke(b: &[u8]) -> Result<(Stroke, Vec<ParseWarning>), ParseError> {
+    let document = CfgDocument::parse(b);
+    let block = document.stroke_blocks().next().ok_or_else(|| ParseError {
+        offset: 0,
+        expected: Some("`StrokeAttributes`".to_string()),
+        message: "expected `StrokeAttributes`".to_string(),
+    })?;
+    let sections = split_sections(&document, block);
+    let mut warnings = Vec::new();
+
+    let header = Fields::new(&document, &sections[0], &HEADER_KEYS, &mut warnings);
+    let mut timings: HashMap<(usize, usize), StrokeTiming> = HashMap::new();
+    for section in &sections[1..] {
+        let (mode, keyframe) = section.keyframe.as_ref().expect("only the header has no keyframe");
+        let mode = MODES.iter().position(|m| m.eq_ignore_ascii_case(mode));
+        let keyframe = keyframe.parse::<usize>().ok().filter(|&k| k < 5);
+        let fields = Fields::new(&document, section, &KEYFRAME_KEYS, &mut warnings);
+        let (mode, keyframe) = match (mode, keyframe) {
+            (Some(mode), Some(keyframe)) => (mode, keyframe),
+            _ => {
+                let message = format!("ignored unknown keyframe `{}`", section.name());
+                warnings.push(ParseWarning { offset: section.start(), message });
+                continue;
+            }
+        };
+        let timing = StrokeTiming {
+            frame: fields.required("Frame")?,
+            vertical: fields.required("Vertical")?,
+            direction: fields.required("Direction")?,
+            direction_area: fields.required("DirectionArea")?,
+            power: fields.required("Power")?,
+            power_area: fields.required("PowerArea")?,
+        };
+        if timings.insert((mode, keyframe), timing).is_some() {
+            let message = format!("{} appears twice, using the last one", section.name());
+            warnings.push(ParseWarning { offset: section.start(), message });
+        }
+    }
+    let mut timing = |mode: usize, keyframe: usize| {
+        timings.remove(&(mode, keyframe)).ok_or_else(|| ParseError {
+            offset: block.span.end,
+            expected: Some(format!("`Mode {} KeyFrame {}`", MODES[mode], keyframe)),
+            message: format!("missing Mode {} KeyFrame {}", MODES[mode], keyframe),
+        })
+    };
+    let mut timings_for = |mode: usize| -> Result<[StrokeTiming; 5], ParseError> {
+        Ok([
+            timing(mode, 0)?,
+            timing(mode, 1)?,
+            timing(mode, 2)?,
+            timing(mode, 3)?,
+            timing(mode, 4)?,
+        ])
+    };
+
+    let stroke = Stroke {
+        stroke_type: header.stroke_type()?,
+        edge_modifier: header.required("EdgeModifier")?,
+        edge_modifier6: header.optional("EdgeModifier6")?,
+        difficulty: header.required("Difficulty")?,
+        reward: header.required("Reward")?,
+        difficulty6: header.required("Difficulty6")?,
+        reward6: header.required("Reward6")?,
+        edge_prob: header.required("EdgeProb")?,
+        edge_prob6: header.required("EdgeProb6")?,
+        bowler_types: header.required("BowlerTypes")?,
+        ball_stumps: header.required("BallStumps")?,
+        ball_batsman: header.required("BallBatsman")?,
+        ball_length: header.required("BallLength")?,
+        timings_normal: timings_for(0)?,
+        timings_6hit: timings_for(1)?,
+    };
+    warnings.extend(layout_warnings(&document, &sections, &stroke));
+    warnings.sort_by_key(|w| w.offset);
+    Ok((stroke, warnings))
+}
+
+/// Finds the value `parse_stroke` would read for `key` in the first stroke block of `b`, from the
+/// header when `keyframe` is `None` and otherwise from that mode and keyframe
+pub fn value_span(b: &[u8], keyframe: Option<(&str, usize)>, key: &str) -> Option<Span> {
+    let document = CfgDocument::parse(b);
+    let block = document.stroke_blocks().next()?;
+    let sections = split_sections(&document, block);
+    // like parse_stroke, the last of any repeated keyframe or key wins
+    let section = sections.iter().rev().find(|s| match (&s.keyframe, keyframe) {
+        (None, None) => true,
+        (Some((m, k)), Some((mode, keyframe))) => {
+            m.eq_ignore_ascii_case(mode) && k.parse() == Ok(keyframe)
+        }
+        _ => false,
+    })?;
+    let entry =
+        section.fields().filter(|e| document.text(e.key).eq_ignore_ascii_case(key.as_bytes()));
+    entry.last()?.value
+}
+
+/// compares the layout of each section with what `Stroke::write` would produce for it
+fn layout_warnings(
+    document: &CfgDocument,
+    sections: &[Section],
+    stroke: &Stroke,
+) -> Vec<ParseWarning> {
+    let canonical_bytes = stroke.to_cfg_bytes(LineEnding::detect(document.source()));
+    let canonical = CfgDocument::parse(&canonical_bytes);
+    let canonical_block = canonical.blocks().first().expect("a written stroke has a block");
+    let canonical_sections = split_sections(&canonical, canonical_block);
+    let keys = |document: &CfgDocument, line: &Line| {
+        let keys: Vec<_> = line.entries.iter().map(|e| document.text_lossy(e.key)).collect();
+        keys.join(" ")
+    };
+
+    let mut warnings = Vec::new();
+    for line in sections.iter().flat_map(|s| s.lines.iter()) {
+        let comment = document
+            .tokens()
+            .iter()
+            .find(|t| t.kind == TokenKind::Comment && line.span.range().contains(&t.span.start));
+        if let Some(comment) = comment {
+            let message = "comment, which Config Editor doesn't write".to_string();
+            warnings.push(ParseWarning { offset: comment.span.start, message });
+        }
+        let end = comment.map_or(line.span.end, |c| c.span.start);
+        let text = document.text_lossy(Span { start: line.span.start, end });
+        let text = if comment.is_some() { text.trim_end() } else { &text };
+        let single_spaced: Vec<_> = line
+            .entries
+            .iter()
+            .flat_map(|e| std::iter::once(e.key).chain(e.value))
+            .map(|s| document.text_lossy(s))
+            .collect();
+        if text != single_spaced.join(" ") {
+            let message = "spacing differs from the single spaces Config Editor writes".to_string();
+            warnings.push(ParseWarning { offset: line.span.start, message });
+        }
+        for value in line.entries.iter().filter_map(|e| e.value) {
+            if document.text(value).starts_with(b"+") {
+                let message = "`+` sign, which Config Editor doesn't write".to_string();
+                warnings.push(ParseWarning { offset: value.start, message });
+            }
+        }
+    }
+    for section in sections {
+        let expected = canonical_sections.iter().find(|c| match (&c.keyframe, &section.keyframe) {
+            (None, None) => true,
+            (Some((cm, ck)), Some((m, k))) => cm.eq_ignore_ascii_case(m) && ck == k,
+            _ => false,
+        });
+        let expected = match expected {
+            Some(expected) => expected,
+            None => continue,
+        };
+        let found = section.lines.iter().map(|l| keys(document, l));
+        let wanted = expected.lines.iter().map(|l| keys(&canonical, l));
+        let mismatch =
+            found.zip(wanted.map(Some).chain(std::iter::repeat(None))).zip(&section.lines).find(
+                |((found, wanted), _)| !matches!(wanted, Some(w) if w.eq_ignore_ascii_case(found)),
+            );
+        if let Some(((found, wanted), line)) = mismatch {
+            let message = match wanted {
+                Some(wanted) => format!(
+                    "found `{}` where Config Editor writes `{}` in {}",
+                    found,
+                    wanted,
+                    section.name()
+                ),
+                None => format!("found `{}` after the end of {}", found, section.name()),
+            };
+            warnings.push(ParseWarning { offset: line.span.start, message });
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_fixtures::{self, stroke_text, HEADER, KEYFRAME};
+
+    /// the fixture stroke with `keyframe` for every keyframe
+    fn stroke(keyframe: &str) -> String {
+        stroke_text(HEADER, keyframe)
+    }
+
+    #[test]
+    fn reads_a_canonical_stroke_without_warnings() {
+        let (stroke, warnings) = parse_stroke(stroke(KEYFRAME).as_bytes()).unwrap();
+        assert_eq!(warnings, []);
+        assert_eq!(stroke, test_fixtures::stroke());
+    }
+
+    #[test]
+    fn accepts_reordered_keys_signed_values_and_comments() {
+        let keyframe = "Vertical  -90596966 // up\nFrame\t{frame}\n\
+            DirectionArea 24298037 Direction +242980370\nPower 144631 PowerArea 361578 \n";
+        let source = stroke(keyframe);
+        let (stroke, warnings) = parse_stroke(source.as_bytes()).unwrap();
+        let expected = parse_stroke(self::stroke(KEYFRAME).as_bytes()).unwrap().0;
+        assert_eq!(stroke.timings_6hit[4].vertical, -90596966);
+        assert_eq!(stroke.timings_6hit[4].direction, expected.timings_6hit[4].direction);
+        assert_eq!(stroke.timings_normal[0].frame, 10);
+
+        let first_keyframe: Vec<_> = warnings
+            .iter()
+            .take(6)
+            .map(|w| (&source[w.offset..w.offset + 5], &w.message[..]))
+            .collect();
+        assert_eq!(
+            first_keyframe,
+            [
+                ("Verti", "spacing differs from the single spaces Config Editor writes"),
+                (
+                    "Verti",
+                    "found `Vertical` where Config Editor writes `Frame` in Mode Normal KeyFrame 0"
+                ),
+                ("// up", "comment, which Config Editor doesn't write"),
+                ("Frame", "spacing differs from the single spaces Config Editor writes"),
+                ("+2429", "`+` sign, which Config Editor doesn't write"),
+                ("Power", "spacing differs from the single spaces Config Editor writes"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_unparseable_values_as_errors() {
+        let missing = stroke(&KEYFRAME.replace("Vertical 90596966\n", ""));
+        let error = parse_stroke(missing.as_bytes()).unwrap_err();
+        assert_eq!(error.message, "missing `Vertical` in Mode Normal KeyFrame 0");
+
+        let garbled = stroke(&KEYFRAME.replace("Frame {frame}", "Frame 1O"));
+        let error = parse_stroke(garbled.as_bytes()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "expected a number after `Frame` in Mode Normal KeyFrame 0, but `1O` is not a whole number"
+        );
+        assert_eq!(&garbled[error.offset..error.offset + 2], "1O");
+    }
+
+    #[test]
+    fn warns_about_unknown_and_repeated_keys() {
+        let source =
+            stroke(&KEYFRAME.replace("Frame {frame}\n", "Frame {frame}\nFrame 12\nSpin 3\n"));
+        let (stroke, warnings) = parse_stroke(source.as_bytes()).unwrap();
+        assert_eq!(stroke.timings_normal[0].frame, 12);
+        let messages: Vec<_> = warnings.iter().take(3).map(|w| &w.message[..]).collect();
+        assert_eq!(
+            messages,
+            [
+                "`Frame` appears twice in Mode Normal KeyFrame 0, using the last one",
+                "found `Frame` where Config Editor writes `Vertical` in Mode Normal KeyFrame 0",
+                "ignored unknown key `Spin` in Mode Normal KeyFrame 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_the_value_that_was_read() {
+        let source = stroke(&KEYFRAME.replace("Frame {frame}\n", "Frame {frame}\nframe 12\n"));
+        let text = |span: Option<Span>| span.map(|s| &source[s.range()]);
+        assert_eq!(text(value_span(source.as_bytes(), Some(("6hit", 3)), "Frame")), Some("12"));
+        assert_eq!(text(value_span(source.as_bytes(), None, "edgemodifier")), Some("10"));
+        assert_eq!(value_span(source.as_bytes(), None, "EdgeModifier6"), None);
+        assert_eq!(value_span(source.as_bytes(), Some(("Normal", 5)), "Frame"), None);
+    }
+}
@@ -1,15 +1,17 @@
-use crate::data::load_cfg_data;
 use crate::pitch_canvas::PitchPainter;
-use crate::strokes::Stroke;
-use crate::Files;
 use anyhow::Context;
 use hotwatch::{Event, Hotwatch};
 use nwg::stretch::geometry::{Rect, Size};
-use nwg::stretch::style::{AlignItems, Dimension as D, FlexDirection};
+use nwg::stretch::style::{AlignItems, Dimension as D, FlexDirection, Style};
 use std::any::Any;
 use std::cell::RefCell;
+use std::cmp::Reverse;
 use std::rc::Rc;
 use std::time::Duration;
+use stroke_preview::data::{load_cfg_data, CfgData, Files, StrokeError};
+use stroke_preview::lint;
+use stroke_preview::sections::Section;
+use stroke_preview::strokes::Stroke;
 use winapi::shared::windef::HWND;
 
 fn default<T: Default>() -> T {
@@ -38,23 +40,34 @@ pub struct Ui {
     list_file_input: nwg::TextInput,
     cfg_file_input: nwg::TextInput,
 
+    checkbox_all_sections: nwg::CheckBox,
     list_select: nwg::ListBox<String>,
+    /// holds either `pitch_canvas` or `section_text`
+    view_flex: nwg::FlexboxLayout,
     pitch_canvas: nwg::ExternCanvas,
+    /// the text of the selected section when it isn't a stroke, shown instead of the pitch
+    section_text: nwg::TextBox,
+    error_label: nwg::Label,
     checkbox_6hit: nwg::CheckBox,
     radios: [nwg::RadioButton; 5],
 
     pitch_painter: Option<PitchPainter>,
 
-    selected_stroke: Option<Stroke>,
+    selected_stroke: Option<Result<Stroke, StrokeError>>,
+    /// the selected stroke from `reference_data` or `previous_data`, if it is different
+    overlay_stroke: Option<Stroke>,
     selected_timing: usize,
 
-    pub cfg_item_offsets: Vec<i64>,
-    pub cfg_contents: Vec<u8>,
+    pub cfg_data: Option<CfgData>,
+    /// the cfg file as it was before the last reload
+    previous_data: Option<CfgData>,
+    reference_data: Option<CfgData>,
     _other_controls_keepalive: Vec<Box<dyn Any>>,
 }
 
 impl Ui {
     fn change_data_files(&mut self, files: Files) -> anyhow::Result<()> {
+        self.watching.files.reference_cfg_file = files.reference_cfg_file;
         let mut changed = false;
         if files.list_file != self.watching.files.list_file {
             let _ = self.watching.watcher.unwatch(&self.watching.files.list_file);
@@ -75,6 +88,8 @@ impl Ui {
             changed = true;
         }
         if changed {
+            // there's nothing to compare a different file with
+            self.cfg_data = None;
             self.load_data_files();
         }
         Ok(())
@@ -84,42 +99,231 @@ impl Ui {
         let new_data = load_cfg_data(&self.watching.files);
         match new_data {
             Ok(new_data) => {
-                self.cfg_item_offsets =
-                    new_data.cfg_items.iter().map(|(_, offset)| *offset).collect();
-                self.cfg_contents = new_data.cfg_contents;
+                for error in &new_data.list_errors {
+                    println!("{}", error);
+                }
+                println!("{}", new_data.calibration);
+                for warning in &new_data.list_warnings {
+                    println!("warning: {}", warning);
+                }
+                for warning in &new_data.location_warnings {
+                    println!("warning: {}", warning);
+                }
+                for i in 0..new_data.cfg_items.len() {
+                    if let Some(warning) = new_data.budget_warning(i) {
+                        println!("warning: {}", warning);
+                    }
+                }
                 let previous_selection = self.list_select.selection();
-                let new_cfg_items_len = new_data.cfg_items.len();
-                self.list_select.set_collection(
-                    new_data
-                        .cfg_items
-                        .into_iter()
-                        .map(|(name, offset)| (line_number_of(&self.cfg_contents, offset), name))
-                        .map(|(line_number, name)| format!("{}: {}", line_number, name))
-                        .collect(),
-                );
-                let previous_selection_if_still_valid =
-                    previous_selection.filter(|&i| i < new_cfg_items_len);
-                self.list_select.set_selection(previous_selection_if_still_valid);
-                self.update_selected_stroke(previous_selection_if_still_valid);
+                self.previous_data = self.cfg_data.replace(new_data);
+                self.reference_data = self.load_reference_data();
+                self.fill_list(previous_selection);
+            }
+            Err(e) => {
+                let message = format!("failed to load data files: {:#}", e);
+                println!("{}", message);
+                self.error_label.set_text(&message);
+            }
+        }
+    }
+
+    fn showing_all_sections(&self) -> bool {
+        self.checkbox_all_sections.check_state() == nwg::CheckBoxState::Checked
+    }
+
+    /// lists the strokes, or every section when "All sections" is checked, and selects
+    /// `selection` if it is still there
+    fn fill_list(&mut self, selection: Option<usize>) {
+        let labels: Vec<String> = match &self.cfg_data {
+            Some(data) if self.showing_all_sections() => {
+                data.sections.iter().map(|s| section_label(data, s)).collect()
+            }
+            Some(data) => (0..data.cfg_items.len()).map(|i| stroke_label(data, i)).collect(),
+            None => Vec::new(),
+        };
+        let selection = selection.filter(|&i| i < labels.len());
+        self.list_select.set_collection(labels);
+        self.list_select.set_selection(selection);
+        self.update_selection(selection);
+    }
+
+    /// switches between listing the strokes and every section, keeping the selected stroke
+    fn toggle_all_sections(&mut self) {
+        let showing_all = self.showing_all_sections();
+        let selection = match (&self.cfg_data, self.list_select.selection()) {
+            (Some(data), Some(i)) if showing_all => {
+                data.sections.iter().position(|s| s.stroke == Some(i))
             }
+            (Some(data), Some(i)) => data.sections.get(i).and_then(|s| s.stroke),
+            _ => None,
+        };
+        self.fill_list(selection);
+    }
+
+    /// shows the pitch for a stroke, or the text of any other section
+    fn update_selection(&mut self, selection_index: Option<usize>) {
+        self.show_section_text(None);
+        if !self.showing_all_sections() {
+            self.update_selected_stroke(selection_index);
+            return;
+        }
+        let (stroke, text) = match (&self.cfg_data, selection_index) {
+            (Some(data), Some(i)) => match data.sections[i].stroke {
+                Some(stroke) => (Some(stroke), None),
+                None => (None, Some(data.section_text(i).map(|t| t.into_owned()))),
+            },
+            _ => (None, None),
+        };
+        self.update_selected_stroke(stroke);
+        match text {
+            Some(Ok(text)) => self.show_section_text(Some(&text)),
+            Some(Err(e)) => self.error_label.set_text(&e.to_string()),
+            None => {}
+        }
+    }
+
+    /// swaps the pitch for a scrollable box showing `text`, or back again for `None`
+    fn show_section_text(&self, text: Option<&str>) {
+        if let Some(text) = text {
+            // edit controls want Windows line breaks and stop at a NUL
+            let text = text.replace("\r\n", "\n").replace('\n', "\r\n").replace('\0', "");
+            self.section_text.set_text(&text);
+        }
+        let showing_text = self.view_flex.has_child(&self.section_text);
+        if text.is_some() == showing_text {
+            return;
+        }
+        let fill = Style {
+            size: Size { width: D::Percent(1.0), height: D::Percent(1.0) },
+            ..Style::default()
+        };
+        if showing_text {
+            self.view_flex.remove_child(&self.section_text);
+            self.view_flex.add_child(&self.pitch_canvas, fill).ok();
+        } else {
+            self.view_flex.remove_child(&self.pitch_canvas);
+            self.view_flex.add_child(&self.section_text, fill).ok();
+        }
+        self.section_text.set_visible(!showing_text);
+        self.pitch_canvas.set_visible(showing_text);
+    }
+
+    fn load_reference_data(&self) -> Option<CfgData> {
+        let files = Files {
+            cfg_file: self.watching.files.reference_cfg_file.clone()?,
+            list_file: self.watching.files.list_file.clone(),
+            reference_cfg_file: None,
+        };
+        match load_cfg_data(&files) {
+            Ok(data) => Some(data),
             Err(e) => {
-                println!("failed to load data files: {:#}", e)
+                println!("failed to load reference cfg file: {:#}", e);
+                None
             }
         }
     }
 
     fn update_selected_stroke(&mut self, selection_index: Option<usize>) {
-        self.selected_stroke = selection_index.map(|i| {
-            parse_stroke(
-                &self.cfg_contents,
-                self.cfg_item_offsets[i],
-                self.cfg_item_offsets.get(i + 1).copied(),
-            )
-        });
+        // hand edited strokes are still worth previewing, so parse leniently and just point out
+        // anything Config Editor wouldn't have written
+        let mut warnings = Vec::new();
+        let (budget_warning, list_warnings, mut findings) = match (&self.cfg_data, selection_index)
+        {
+            (Some(data), Some(i)) => {
+                let list_warnings: Vec<String> =
+                    data.list_warnings_of(i).map(|w| w.to_string()).collect();
+                (data.budget_warning(i), list_warnings, data.lint_findings[i].clone())
+            }
+            _ => (None, Vec::new(), Vec::new()),
+        };
+        self.selected_stroke = match (&self.cfg_data, selection_index) {
+            (Some(data), Some(i)) => Some(data.parse_stroke_tolerant(i).map(|(stroke, w)| {
+                warnings = w;
+                stroke
+            })),
+            _ => None,
+        };
+        // outline the same stroke from the reference file, or as it was before the last reload,
+        // so it's clear how an edit moved it
+        let selected = self.selected_stroke.as_ref().and_then(|s| s.as_ref().ok());
+        self.overlay_stroke = match (&self.cfg_data, selection_index) {
+            (Some(data), Some(i)) => {
+                let other = self.reference_data.as_ref().or_else(|| self.previous_data.as_ref());
+                other
+                    .and_then(|other| {
+                        let j = other.position_of(&data.cfg_items[i].description)?;
+                        other.parse_stroke_tolerant(j).ok().map(|(stroke, _)| stroke)
+                    })
+                    .filter(|overlay| Some(overlay) != selected)
+            }
+            _ => None,
+        };
+        let message = match &self.selected_stroke {
+            Some(Err(e)) => match &e.snippet {
+                // static controls want Windows line breaks
+                Some(snippet) => format!("{}\r\n{}", e, snippet.replace('\n', "\r\n")),
+                None => e.to_string(),
+            },
+            Some(Ok(_)) => {
+                // a stroke too long for its slot can't be imported at all, and one whose offset
+                // clashes may not be the stroke it's named as, so those come first, then lint
+                // findings about what the stroke does in the game, errors before warnings
+                findings.sort_by_key(|f| Reverse(f.severity));
+                let problems: Vec<String> = budget_warning
+                    .iter()
+                    .chain(&list_warnings)
+                    .map(|w| format!("warning: {}", w))
+                    .chain(findings.iter().map(|f| f.to_string()))
+                    .chain(warnings.iter().map(|w| format!("warning: {}", w)))
+                    .collect();
+                match problems.as_slice() {
+                    [] => String::new(),
+                    [problem] => problem.clone(),
+                    [first, rest @ ..] => format!("{}\r\n(and {} more)", first, rest.len()),
+                }
+            }
+            None => String::new(),
+        };
+        self.error_label.set_text(&message);
         self.pitch_canvas.invalidate();
     }
 }
 
+/// the list label of the `i`th stroke, with its line number and any problems
+fn stroke_label(data: &CfgData, i: usize) -> String {
+    let label = format!("{}: {}", data.line_label(i), data.cfg_items[i].description);
+    match problem_summary(data, i) {
+        summary if summary.is_empty() => label,
+        summary => format!("{}  [{}]", label, summary),
+    }
+}
+
+/// the list label of a section, with its category and line number, and any problems if it's a
+/// stroke
+fn section_label(data: &CfgData, section: &Section) -> String {
+    let line = section.line_number.map_or_else(|| "-".to_string(), |l| l.to_string());
+    let label = format!("{}: {}", line, section);
+    match section.stroke.map(|i| problem_summary(data, i)) {
+        Some(summary) if !summary.is_empty() => format!("{}  [{}]", label, summary),
+        _ => label,
+    }
+}
+
+/// whether the `i`th stroke is too long for its slot or its offset clashes with another entry,
+/// and how many lint errors and warnings it has if it parses
+fn problem_summary(data: &CfgData, i: usize) -> String {
+    let mut parts = Vec::new();
+    if data.budget_warning(i).is_some() {
+        parts.push("too long".to_string());
+    }
+    if data.list_warnings_of(i).next().is_some() {
+        parts.push("offset clash".to_string());
+    }
+    parts.push(lint::summary(&data.lint_findings[i]));
+    parts.retain(|part| !part.is_empty());
+    parts.join(", ")
+}
+
 pub struct App {
     pub ui: Rc<RefCell<Ui>>,
     handlers: [nwg::EventHandler; 1],
@@ -155,13 +359,38 @@ impl App {
             .title("Stroke preview")
             .build(&mut window)?;
 
+        let mut left_frame = default();
+        nwg::Frame::builder()
+            .parent(&window)
+            .flags(nwg::FrameFlags::VISIBLE)
+            .build(&mut left_frame)?;
+
+        let mut checkbox_all_sections = default();
+        nwg::CheckBox::builder()
+            .parent(&left_frame)
+            .text("All sections")
+            .build(&mut checkbox_all_sections)?;
+
         let mut list_select = default();
         nwg::ListBox::builder()
             .collection(Vec::new())
             .size((300, 10))
-            .parent(&window)
+            .parent(&left_frame)
             .build(&mut list_select)?;
 
+        let left_flex = default();
+        nwg::FlexboxLayout::builder()
+            .parent(&left_frame)
+            .flex_direction(FlexDirection::Column)
+            .padding(rect(0.0))
+            .child(&checkbox_all_sections)
+            .child_size(Size { width: D::Percent(1.0), height: D::Points(30.0) })
+            .child_flex_grow(0.0)
+            .child_flex_shrink(0.0)
+            .child(&list_select)
+            .child_size(Size { width: D::Percent(1.0), height: D::Percent(1.0) })
+            .build(&left_flex)?;
+
         // Layouts
 
         let mut right_frame = default();
@@ -219,8 +448,41 @@ impl App {
         }
         flex_builder.build(&radios_flex)?;
 
+        let mut view_frame = default();
+        nwg::Frame::builder()
+            .parent(&right_frame)
+            .flags(nwg::FrameFlags::VISIBLE)
+            .build(&mut view_frame)?;
+
         let mut pitch_canvas = default();
-        nwg::ExternCanvas::builder().parent(Some(&right_frame)).build(&mut pitch_canvas)?;
+        nwg::ExternCanvas::builder().parent(Some(&view_frame)).build(&mut pitch_canvas)?;
+
+        // monospaced, so the caret under the offending line lines up
+        let mut error_font = default();
+        nwg::Font::builder().family("Consolas").size(16).build(&mut error_font)?;
+
+        // hidden until a section other than a stroke is selected
+        let mut section_text = default();
+        nwg::TextBox::builder()
+            .parent(&view_frame)
+            .flags(nwg::TextBoxFlags::VSCROLL | nwg::TextBoxFlags::HSCROLL)
+            .readonly(true)
+            .font(Some(&error_font))
+            .build(&mut section_text)?;
+
+        let view_flex = default();
+        nwg::FlexboxLayout::builder()
+            .parent(&view_frame)
+            .padding(rect(0.0))
+            .child(&pitch_canvas)
+            .child_size(Size { width: D::Percent(1.0), height: D::Percent(1.0) })
+            .build(&view_flex)?;
+        let mut error_label = default();
+        nwg::Label::builder()
+            .parent(&right_frame)
+            .text("")
+            .font(Some(&error_font))
+            .build(&mut error_label)?;
 
         let mut cfg_file_frame = default();
         nwg::Frame::builder()
@@ -287,9 +549,14 @@ impl App {
             .child_margin(rect(0.0))
             .child_flex_grow(0.0)
             .child_flex_shrink(0.0)
-            .child(&pitch_canvas)
+            .child(&view_frame)
             .child_size(Size { width: D::Percent(1.0), height: D::Percent(1.0) })
             .child_margin(rect(5.0))
+            .child(&error_label)
+            .child_size(Size { width: D::Percent(1.0), height: D::Points(50.0) })
+            .child_margin(rect(0.0))
+            .child_flex_grow(0.0)
+            .child_flex_shrink(0.0)
             .child(&radios_frame)
             .child_size(Size { width: D::Points(450.0), height: D::Points(40.0) })
             .child_margin(rect(5.0))
@@ -298,7 +565,7 @@ impl App {
         let root = default();
         nwg::FlexboxLayout::builder()
             .parent(&window)
-            .child(&list_select)
+            .child(&left_frame)
             .child_flex_grow(1.0)
             .child(&right_frame)
             .child_flex_grow(2.0)
@@ -311,22 +578,33 @@ impl App {
         let mut ui = Ui {
             watching: Watching {
                 watcher: Hotwatch::new_with_custom_delay(Duration::from_millis(200))?,
-                files: Files { list_file: String::new(), cfg_file: String::new() },
+                files: Files {
+                    list_file: String::new(),
+                    cfg_file: String::new(),
+                    reference_cfg_file: None,
+                },
             },
             window,
             notice_receiver,
             list_file_input,
             cfg_file_input,
+            checkbox_all_sections,
             list_select,
+            view_flex,
             pitch_canvas,
+            section_text,
+            error_label,
             checkbox_6hit,
             radios,
             pitch_painter: None,
             selected_stroke: None,
+            overlay_stroke: None,
             selected_timing: 2,
-            cfg_item_offsets: Vec::new(),
-            cfg_contents: Vec::new(),
+            cfg_data: None,
+            previous_data: None,
+            reference_data: None,
             _other_controls_keepalive: vec![
+                Box::new(error_font),
                 Box::new(cfg_file_label),
                 Box::new(cfg_file_flex),
                 Box::new(cfg_file_frame),
@@ -334,6 +612,9 @@ impl App {
                 Box::new(list_file_flex),
                 Box::new(list_file_frame),
                 Box::new(radios_frame),
+                Box::new(view_frame),
+                Box::new(left_flex),
+                Box::new(left_frame),
                 Box::new(right_flex),
                 Box::new(right_frame),
                 Box::new(root),
@@ -362,7 +643,8 @@ impl App {
                             if let Some(painter) = &ui.pitch_painter {
                                 painter.paint(
                                     data.on_paint(),
-                                    ui.selected_stroke.as_ref(),
+                                    ui.selected_stroke.as_ref().and_then(|s| s.as_ref().ok()),
+                                    ui.overlay_stroke.as_ref(),
                                     ui.selected_timing,
                                     ui.checkbox_6hit.check_state() == nwg::CheckBoxState::Checked,
                                 );
@@ -370,13 +652,15 @@ impl App {
                         }
                         E::OnListBoxSelect if h == ui.list_select => {
                             let i = ui.list_select.selection();
-                            ui.update_selected_stroke(i);
+                            ui.update_selection(i);
                         }
                         E::OnTextInput if h == ui.cfg_file_input || h == ui.list_file_input => {
                             let list_file = ui.list_file_input.text();
                             let cfg_file = ui.cfg_file_input.text();
                             // this does IO on the main thread, but it won't be that slow
-                            if let Err(e) = ui.change_data_files(Files { list_file, cfg_file }) {
+                            let reference_cfg_file = ui.watching.files.reference_cfg_file.clone();
+                            let files = Files { list_file, cfg_file, reference_cfg_file };
+                            if let Err(e) = ui.change_data_files(files) {
                                 println!("error changing data files: {:#}", e);
                             };
                         }
@@ -387,6 +671,8 @@ impl App {
                                 ui.pitch_canvas.invalidate();
                             } else if ui.checkbox_6hit == h {
                                 ui.pitch_canvas.invalidate()
+                            } else if ui.checkbox_all_sections == h {
+                                ui.toggle_all_sections();
                             }
                         }
                         _ => {}
@@ -411,30 +697,3 @@ fn watch_callback(notice_receiver: &nwg::Notice) -> impl FnMut(Event) + Send + '
         }
     }
 }
-
-fn line_number_of(cfg_contents: &[u8], offset: i64) -> usize {
-    // offset found experimentally
-    let bytes_of_non_strokes: i64 = 0x7c60;
-    // https://www.planetcricket.org/forums/threads/config-editor-v3.8697/post-130389
-    // offset of first stroke
-    let delta = -558891009 + bytes_of_non_strokes;
-    let offset = offset + delta;
-    cfg_contents[..(offset as usize)].iter().filter(|&&c| c == b'\n').count() + 1
-}
-
-fn parse_stroke(cfg_contents: &[u8], offset: i64, offset_next: Option<i64>) -> Stroke {
-    // offset found experimentally
-    let bytes_of_non_strokes: i64 = 0x7c60;
-    // https://www.planetcricket.org/forums/threads/config-editor-v3.8697/post-130389
-    // offset of first stroke
-    let delta = -558891009 + bytes_of_non_strokes;
-    let offset = offset + delta;
-    let slice = if let Some(end) = offset_next {
-        let end = end + delta;
-        &cfg_contents[(offset as usize)..(end as usize)]
-    } else {
-        &cfg_contents[(offset as usize)..]
-    };
-    String::from_utf8_lossy(slice);
-    Stroke::parse(slice).unwrap()
-}